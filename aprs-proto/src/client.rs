@@ -1,12 +1,16 @@
-use aprs_value::Value;
 use serde::{Deserialize, Serialize};
-use smallvec::SmallVec;
+use strum::{IntoStaticStr, VariantNames};
+
+use crate::server::InvalidPacket;
 
 mod connect;
 pub use connect::{Connect, ItemsHandling};
 
+mod connect_update;
+pub use connect_update::ConnectUpdate;
+
 mod location_scouts;
-pub use location_scouts::LocationScouts;
+pub use location_scouts::{CreateAsHint, LocationScouts};
 
 mod say;
 pub use say::Say;
@@ -35,12 +39,18 @@ pub use get_data_package::GetDataPackage;
 mod bounce;
 pub use bounce::Bounce;
 
-pub type Messages = SmallVec<[Message; 1]>;
+mod update_hint;
+pub use update_hint::UpdateHint;
+
+mod create_hints;
+pub use create_hints::CreateHints;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The variant names are the `cmd`s, for both serde and strum
+#[derive(Serialize, Deserialize, IntoStaticStr, VariantNames, Debug)]
 #[serde(tag = "cmd")]
 pub enum Message {
     Connect(Connect),
+    ConnectUpdate(ConnectUpdate),
     Get(Get),
     Set(Set),
     SetNotify(SetNotify),
@@ -51,8 +61,37 @@ pub enum Message {
     GetDataPackage(GetDataPackage),
     StatusUpdate(StatusUpdate),
     Bounce(Bounce),
-    #[serde(untagged)]
-    Unknown(Value),
+    UpdateHint(UpdateHint),
+    CreateHints(CreateHints),
+}
+
+impl Message {
+    /// Returns the `cmd` of the message
+    pub fn cmd(&self) -> &'static str {
+        self.into()
+    }
+
+    /// Decodes a single message.
+    /// The error describes why the message is invalid.
+    pub fn decode(value: serde_json::Value) -> Result<Message, InvalidPacket> {
+        let Some(cmd) = value.get("cmd").and_then(|cmd| cmd.as_str()) else {
+            return Err(InvalidPacket::cmd(
+                None,
+                "Packet is missing a string `cmd` field",
+            ));
+        };
+        let cmd = cmd.to_owned();
+
+        if !Message::VARIANTS.contains(&cmd.as_str()) {
+            let text = format!("Unknown cmd `{cmd}`");
+            return Err(InvalidPacket::cmd(Some(cmd), text));
+        }
+
+        Message::deserialize(value).map_err(|err| {
+            let text = format!("Invalid arguments for `{cmd}`: {err}");
+            InvalidPacket::arguments(cmd, text)
+        })
+    }
 }
 
 impl From<Connect> for Message {
@@ -61,6 +100,12 @@ impl From<Connect> for Message {
     }
 }
 
+impl From<ConnectUpdate> for Message {
+    fn from(value: ConnectUpdate) -> Self {
+        Message::ConnectUpdate(value)
+    }
+}
+
 impl From<Get> for Message {
     fn from(value: Get) -> Self {
         Message::Get(value)
@@ -120,3 +165,102 @@ impl From<Bounce> for Message {
         Message::Bounce(value)
     }
 }
+
+impl From<UpdateHint> for Message {
+    fn from(value: UpdateHint) -> Self {
+        Message::UpdateHint(value)
+    }
+}
+
+impl From<CreateHints> for Message {
+    fn from(value: CreateHints) -> Self {
+        Message::CreateHints(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::server::{KnownPacketProblemType, PacketProblemType};
+
+    /// A valid packet for `cmd`
+    fn packet(cmd: &str) -> serde_json::Value {
+        let mut packet = match cmd {
+            "Connect" => json!({
+                "password": null,
+                "game": "Clique",
+                "name": "Player1",
+                "uuid": "1234",
+                "version": {"major": 0, "minor": 6, "build": 1, "class": "Version"},
+                "items_handling": 7,
+                "tags": ["AP"],
+                "slot_data": true,
+            }),
+            "ConnectUpdate" => json!({"tags": ["AP", "DeathLink"]}),
+            "Get" => json!({"keys": ["key"]}),
+            "Set" => json!({"key": "key", "operations": [{"operation": "add", "value": 1}]}),
+            "SetNotify" => json!({"keys": ["key"]}),
+            "Say" => json!({"text": "hi"}),
+            "Sync" => json!({}),
+            "LocationScouts" => json!({"locations": [1, 2]}),
+            "LocationChecks" => json!({"locations": [1, 2]}),
+            "GetDataPackage" => json!({"games": ["Clique"]}),
+            "StatusUpdate" => json!({"status": 30}),
+            "Bounce" => json!({"tags": ["DeathLink"], "data": {"time": 0}}),
+            "UpdateHint" => json!({"player": 1, "location": 1, "status": 30}),
+            "CreateHints" => json!({"locations": [1, 2]}),
+            _ => panic!("no packet for `{cmd}`"),
+        };
+
+        packet["cmd"] = cmd.into();
+        packet
+    }
+
+    const CMD: PacketProblemType = PacketProblemType::Known(KnownPacketProblemType::Cmd);
+    const ARGUMENTS: PacketProblemType =
+        PacketProblemType::Known(KnownPacketProblemType::Arguments);
+
+    #[test]
+    fn every_cmd_round_trips() {
+        for &cmd in Message::VARIANTS {
+            let message = Message::decode(packet(cmd))
+                .unwrap_or_else(|err| panic!("failed to decode `{cmd}`: {}", err.text));
+
+            assert_eq!(message.cmd(), cmd);
+        }
+    }
+
+    #[test]
+    fn missing_or_non_string_cmd() {
+        for packet in [json!({"text": "hi"}), json!({"cmd": 1}), json!([])] {
+            let err = Message::decode(packet).unwrap_err();
+
+            assert_eq!(err.r#type, CMD);
+            assert_eq!(err.original_cmd, None);
+            assert_eq!(err.text, "Packet is missing a string `cmd` field");
+        }
+    }
+
+    #[test]
+    fn unknown_cmd() {
+        let err = Message::decode(json!({"cmd": "Dance"})).unwrap_err();
+
+        assert_eq!(err.r#type, CMD);
+        assert_eq!(err.original_cmd.as_deref(), Some("Dance"));
+        assert_eq!(err.text, "Unknown cmd `Dance`");
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let err = Message::decode(json!({"cmd": "Say", "text": 1})).unwrap_err();
+
+        assert_eq!(err.r#type, ARGUMENTS);
+        assert_eq!(err.original_cmd.as_deref(), Some("Say"));
+        assert_eq!(
+            err.text,
+            "Invalid arguments for `Say`: invalid type: integer `1`, expected a string"
+        );
+    }
+}
@@ -52,4 +52,32 @@ impl ItemsHandling {
     pub fn is_starting_inventory(&self) -> bool {
         self.contains(Self::StartingInventory)
     }
+
+    /// Returns `false` if undefined bits are set
+    /// or if `OwnWorld` or `StartingInventory` are set without `Remote`.
+    pub fn is_valid(&self) -> bool {
+        let requires_remote = Self::OwnWorld | Self::StartingInventory;
+
+        Self::from_bits(self.bits()).is_some()
+            && (self.is_remote() || !self.intersects(requires_remote))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_items_handling() {
+        for bits in [0b000, 0b001, 0b011, 0b101, 0b111] {
+            assert!(ItemsHandling(bits).is_valid(), "{bits:#05b}");
+        }
+    }
+
+    #[test]
+    fn invalid_items_handling() {
+        for bits in [0b010, 0b100, 0b110, 0b1000, 0b1001, 0xff] {
+            assert!(!ItemsHandling(bits).is_valid(), "{bits:#05b}");
+        }
+    }
 }
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::client::ItemsHandling;
+
+/// Updates the tags and/or items handling of an already connected client.
+/// Omitted fields are left unchanged.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConnectUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items_handling: Option<ItemsHandling>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{LocationId, SlotId};
+use crate::server::print_json::HintStatus;
+
+/// Creates hints for the given locations
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateHints {
+    pub locations: Vec<LocationId>,
+    /// The slot the locations belong to. Defaults to the sending slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<SlotId>,
+    #[serde(default)]
+    pub status: HintStatus,
+}
@@ -1,9 +1,29 @@
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::primitives::LocationId;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LocationScouts {
     pub locations: Vec<LocationId>,
-    pub create_as_hint: i32,
+    #[serde(default)]
+    pub create_as_hint: CreateAsHint,
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, PartialEq, Eq, Default, Debug)]
+#[repr(u8)]
+pub enum CreateAsHint {
+    /// Only scout the locations
+    #[default]
+    No = 0,
+    /// Create hints for the scouted locations and broadcast them
+    Broadcast = 1,
+    /// Create hints for the scouted locations without broadcasting them
+    Silent = 2,
+}
+
+impl CreateAsHint {
+    pub fn creates_hints(&self) -> bool {
+        !matches!(self, CreateAsHint::No)
+    }
 }
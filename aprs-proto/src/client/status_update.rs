@@ -6,12 +6,25 @@ pub struct StatusUpdate {
     pub status: ClientStatus,
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, PartialEq, Eq, Default, Debug)]
 #[repr(u8)]
 pub enum ClientStatus {
+    #[default]
     Unknown = 0,
     Connected = 5,
     Ready = 10,
     Playing = 20,
     Goal = 30,
 }
+
+impl ClientStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientStatus::Unknown => "Unknown",
+            ClientStatus::Connected => "Connected",
+            ClientStatus::Ready => "Ready",
+            ClientStatus::Playing => "Playing",
+            ClientStatus::Goal => "Goal Complete",
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{LocationId, SlotId};
+use crate::server::print_json::HintStatus;
+
+/// Changes the status of an existing hint
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateHint {
+    /// The slot the hinted location belongs to
+    pub player: SlotId,
+    pub location: LocationId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<HintStatus>,
+}
@@ -1,6 +1,9 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, Deserialize)]
+/// Versions are ordered by `major`, `minor` and then `build`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Deserialize)]
 pub struct NetworkVersion {
     pub major: u32,
     pub minor: u32,
@@ -39,7 +42,7 @@ impl Serialize for NetworkVersion {
 }
 
 impl NetworkVersion {
-    pub fn new(major: u32, minor: u32, build: u32) -> Self {
+    pub const fn new(major: u32, minor: u32, build: u32) -> Self {
         Self {
             major,
             minor,
@@ -48,6 +51,12 @@ impl NetworkVersion {
     }
 }
 
+impl fmt::Display for NetworkVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)
+    }
+}
+
 impl From<(u32, u32, u32)> for NetworkVersion {
     fn from((major, minor, build): (u32, u32, u32)) -> Self {
         Self {
@@ -56,6 +56,9 @@ pub use data_package::{DataPackage, DataPackageData};
 mod game_data;
 pub use game_data::GameData;
 
+mod hint;
+pub use hint::Hint;
+
 mod invalid_packet;
 pub use invalid_packet::{InvalidPacket, KnownPacketProblemType, PacketProblemType};
 
@@ -113,6 +116,12 @@ impl From<Bounced> for Arc<Message> {
     }
 }
 
+impl From<InvalidPacket> for Arc<Message> {
+    fn from(value: InvalidPacket) -> Self {
+        Arc::new(Message::InvalidPacket(value))
+    }
+}
+
 impl From<LocationInfo> for Arc<Message> {
     fn from(value: LocationInfo) -> Self {
         Arc::new(Message::LocationInfo(value))
@@ -23,6 +23,12 @@ impl ConnectionRefused {
     }
 }
 
+impl From<Vec<ConnectionError>> for ConnectionRefused {
+    fn from(errors: Vec<ConnectionError>) -> Self {
+        Self { errors }
+    }
+}
+
 impl From<ConnectionError> for ConnectionRefused {
     fn from(value: ConnectionError) -> Self {
         Self {
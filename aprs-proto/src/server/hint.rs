@@ -0,0 +1,113 @@
+use aprs_value::{Dict, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{ItemId, LocationId, SlotId};
+use crate::server::NetworkItem;
+use crate::server::print_json::HintStatus;
+
+// field order MUST match the pickled `NetUtils.Hint` named tuple
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub receiving_player: SlotId,
+    pub finding_player: SlotId,
+    pub location: LocationId,
+    pub item: ItemId,
+    pub found: bool,
+    #[serde(default)]
+    pub entrance: String,
+    #[serde(default)]
+    pub item_flags: u64,
+    #[serde(default)]
+    pub status: HintStatus,
+}
+
+impl Hint {
+    /// Returns `true` if both hints point at the same location
+    pub fn is_same_location(&self, other: &Hint) -> bool {
+        self.finding_player == other.finding_player && self.location == other.location
+    }
+
+    pub fn network_item(&self) -> NetworkItem {
+        NetworkItem {
+            item: self.item,
+            location: self.location,
+            player: self.finding_player,
+            flags: self.item_flags,
+        }
+    }
+
+    pub fn mark_found(&mut self) {
+        self.found = true;
+        self.status = HintStatus::Found;
+    }
+}
+
+impl Serialize for Hint {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Hint {
+            receiving_player,
+            finding_player,
+            location,
+            item,
+            found,
+            ref entrance,
+            item_flags,
+            status,
+        } = *self;
+
+        // The python client expects a "class" field in the json serialization
+        #[derive(Serialize)]
+        #[serde(tag = "class", rename = "Hint")]
+        struct PythonHint<'a> {
+            pub receiving_player: SlotId,
+            pub finding_player: SlotId,
+            pub location: LocationId,
+            pub item: ItemId,
+            pub found: bool,
+            pub entrance: &'a str,
+            pub item_flags: u64,
+            pub status: HintStatus,
+        }
+
+        PythonHint {
+            receiving_player,
+            finding_player,
+            location,
+            item,
+            found,
+            entrance,
+            item_flags,
+            status,
+        }
+        .serialize(ser)
+    }
+}
+
+impl From<&Hint> for Value {
+    fn from(hint: &Hint) -> Self {
+        let dict = Dict::new();
+
+        {
+            let mut dict = dict.write();
+            let mut insert = |key: &str, value: Value| {
+                dict.insert(key, value)
+                    .expect("BUG: string keys are always hashable")
+            };
+
+            insert("class", Value::str("Hint"));
+            insert("receiving_player", Value::int(hint.receiving_player.0));
+            insert("finding_player", Value::int(hint.finding_player.0));
+            insert("location", Value::int(hint.location.0));
+            insert("item", Value::int(hint.item.0));
+            insert("found", Value::bool(hint.found));
+            insert("entrance", Value::str(hint.entrance.as_str()));
+            insert("item_flags", Value::int(hint.item_flags));
+            insert("status", Value::int(hint.status as u32));
+        }
+
+        dict.into()
+    }
+}
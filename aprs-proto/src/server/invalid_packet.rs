@@ -7,14 +7,34 @@ pub struct InvalidPacket {
     pub text: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl InvalidPacket {
+    /// The command itself was unknown or not allowed
+    pub fn cmd(original_cmd: Option<String>, text: impl Into<String>) -> Self {
+        Self {
+            r#type: PacketProblemType::Known(KnownPacketProblemType::Cmd),
+            original_cmd,
+            text: text.into(),
+        }
+    }
+
+    /// The arguments of `original_cmd` were invalid
+    pub fn arguments(original_cmd: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            r#type: PacketProblemType::Known(KnownPacketProblemType::Arguments),
+            original_cmd: Some(original_cmd.into()),
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(untagged)]
 pub enum PacketProblemType {
     Known(KnownPacketProblemType),
     Unknown(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum KnownPacketProblemType {
     Cmd,
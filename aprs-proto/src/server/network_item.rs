@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::primitives::{ItemId, LocationId, SlotId};
 
-#[derive(Copy, Clone, Debug, Hash, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Deserialize)]
 pub struct NetworkItem {
     pub item: ItemId,
     pub location: LocationId,
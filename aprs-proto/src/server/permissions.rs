@@ -28,3 +28,26 @@ pub enum RemainingCommandPermission {
     Enabled = 0b001,  // 1, allows manual use
     Goal = 0b010,     // 2, allows manual use after goal completion
 }
+
+impl CommandPermission {
+    /// Returns `true` if the command is forced on goal completion
+    pub fn is_auto(&self) -> bool {
+        *self as u8 & 0b100 != 0
+    }
+
+    /// Returns `true` if a player may use the command manually
+    pub fn allows_manual_use(&self, goal_completed: bool) -> bool {
+        let bits = *self as u8;
+
+        bits & 0b001 != 0 || (bits & 0b010 != 0 && goal_completed)
+    }
+}
+
+impl RemainingCommandPermission {
+    /// Returns `true` if a player may use the command manually
+    pub fn allows_manual_use(&self, goal_completed: bool) -> bool {
+        let bits = *self as u8;
+
+        bits & 0b001 != 0 || (bits & 0b010 != 0 && goal_completed)
+    }
+}
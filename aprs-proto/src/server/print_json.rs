@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize, Serializer};
-use serde_repr::Serialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::primitives::{ItemId, LocationId, SlotId};
-use crate::server::NetworkItem;
+use crate::common::NetworkVersion;
+use crate::primitives::{ItemId, LocationId, SlotId, TeamId};
+use crate::server::{Hint, NetworkItem};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PrintJson {
@@ -26,6 +27,31 @@ impl PrintJson {
         }
     }
 
+    pub fn admin_command_result(text: impl Into<String>) -> Self {
+        Self {
+            data: vec![JsonMessagePart::chat_message(text)],
+            additional_info: Some(AdditionalInfo {
+                r#type: Some(Type::AdminCommandResult),
+                ..<_>::default()
+            }),
+        }
+    }
+
+    pub fn countdown(text: impl Into<String>, countdown: u32) -> Self {
+        Self {
+            data: vec![JsonMessagePart::chat_message(text)],
+            additional_info: Some(AdditionalInfo {
+                r#type: Some(Type::Countdown),
+                countdown: Some(countdown),
+                ..<_>::default()
+            }),
+        }
+    }
+
+    pub fn command_result(text: impl Into<String>) -> Self {
+        PrintJson::builder().with_text(text).build_command_result()
+    }
+
     pub fn chat_message_for_received_item(item: NetworkItem, receiving_slot: SlotId) -> PrintJson {
         let mut message = PrintJson::builder().with_player(item.player);
 
@@ -43,6 +69,101 @@ impl PrintJson {
 
         message.build_item_send(receiving_slot, item)
     }
+
+    pub fn release(team: TeamId, slot: SlotId) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(" has released all remaining items from their world.")
+            .build_for_player(Type::Release, team, slot)
+    }
+
+    pub fn goal(team: TeamId, slot: SlotId) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(" has completed their goal.")
+            .build_for_player(Type::Goal, team, slot)
+    }
+
+    pub fn collect(team: TeamId, slot: SlotId) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(" has collected their items from other worlds.")
+            .build_for_player(Type::Collect, team, slot)
+    }
+
+    pub fn join(
+        team: TeamId,
+        slot: SlotId,
+        game: &str,
+        version: NetworkVersion,
+        tags: Vec<String>,
+    ) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(format!(
+                " (Team #{}) playing {game} has joined. Client({version}), {}.",
+                team.0 + 1,
+                python_list(&tags)
+            ))
+            .build_for_player_with_tags(Type::Join, team, slot, tags)
+    }
+
+    pub fn part(
+        team: TeamId,
+        slot: SlotId,
+        version: NetworkVersion,
+        tags: Vec<String>,
+    ) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(format!(
+                " (Team #{}) has left the game. Client({version}), {}.",
+                team.0 + 1,
+                python_list(&tags)
+            ))
+            .build_for_player_with_tags(Type::Part, team, slot, tags)
+    }
+
+    pub fn tags_changed(
+        team: TeamId,
+        slot: SlotId,
+        old_tags: &[String],
+        new_tags: Vec<String>,
+    ) -> PrintJson {
+        PrintJson::builder()
+            .with_player(slot)
+            .with_text(format!(
+                " (Team #{}) has changed tags from {} to {}.",
+                team.0 + 1,
+                python_list(old_tags),
+                python_list(&new_tags)
+            ))
+            .build_for_player_with_tags(Type::TagsChanged, team, slot, new_tags)
+    }
+
+    pub fn hint(hint: &Hint) -> PrintJson {
+        let mut message = PrintJson::builder()
+            .with_text("[Hint]: ")
+            .with_player(hint.receiving_player)
+            .with_text("'s ")
+            .with_item(hint.receiving_player, hint.item, hint.item_flags)
+            .with_text(" is at ")
+            .with_location(hint.finding_player, hint.location)
+            .with_text(" in ")
+            .with_player(hint.finding_player);
+
+        if hint.entrance.is_empty() {
+            message.add_text("'s World");
+        } else {
+            message.add_text("'s World at ");
+            message.add_entrance(&hint.entrance);
+        }
+
+        message.add_text(". ");
+        message.add_hint_status(hint.status);
+
+        message.build_hint(hint)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -88,10 +209,44 @@ pub enum JsonMessagePart {
         #[serde(rename = "text", serialize_with = "slot_id_as_string")]
         player_id: SlotId,
     },
+    EntranceName {
+        text: String,
+    },
+    HintStatus {
+        text: String,
+        hint_status: HintStatus,
+    },
     #[serde(untagged)]
     Other(serde_json::Value),
 }
 
+/// Formats the strings like Python's `repr` of a list, e.g. `['AP', 'DeathLink']`,
+/// which is what Archipelago shows in chat
+fn python_list(strings: &[String]) -> String {
+    let strings = strings.iter().map(|string| {
+        // like Python, only use double quotes if that avoids escaping
+        let quote = if string.contains('\'') && !string.contains('"') {
+            '"'
+        } else {
+            '\''
+        };
+        let mut repr = String::from(quote);
+
+        for c in string.chars() {
+            if c == quote || c == '\\' {
+                repr.push('\\');
+            }
+
+            repr.push(c);
+        }
+
+        repr.push(quote);
+        repr
+    });
+
+    format!("[{}]", strings.collect::<Vec<_>>().join(", "))
+}
+
 fn item_id_as_string<S: Serializer>(item_id: &ItemId, ser: S) -> Result<S::Ok, S::Error> {
     ser.serialize_str(&item_id.0.to_string())
 }
@@ -150,9 +305,10 @@ pub enum Type {
     Other(String),
 }
 
-#[derive(Serialize_repr, Clone, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Copy, Clone, PartialEq, Eq, Default, Debug)]
 #[repr(u32)]
 pub enum HintStatus {
+    #[default]
     Unspecified = 0,
     NoPriority = 10,
     Avoid = 20,
@@ -160,6 +316,18 @@ pub enum HintStatus {
     Found = 40,
 }
 
+impl HintStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HintStatus::Unspecified => "(unspecified)",
+            HintStatus::NoPriority => "(no priority)",
+            HintStatus::Avoid => "(avoid)",
+            HintStatus::Priority => "(priority)",
+            HintStatus::Found => "(found)",
+        }
+    }
+}
+
 pub struct MessageBuilder {
     parts: Vec<JsonMessagePart>,
 }
@@ -217,6 +385,19 @@ impl MessageBuilder {
         self
     }
 
+    pub fn add_entrance(&mut self, entrance: impl Into<String>) {
+        self.parts.push(JsonMessagePart::EntranceName {
+            text: entrance.into(),
+        });
+    }
+
+    pub fn add_hint_status(&mut self, hint_status: HintStatus) {
+        self.parts.push(JsonMessagePart::HintStatus {
+            text: hint_status.name().into(),
+            hint_status,
+        });
+    }
+
     pub fn build(self) -> PrintJson {
         PrintJson {
             data: self.parts,
@@ -234,6 +415,57 @@ impl MessageBuilder {
             }),
         }
     }
+
+    pub fn build_command_result(self) -> PrintJson {
+        PrintJson {
+            data: self.parts,
+            additional_info: Some(AdditionalInfo {
+                r#type: Some(Type::CommandResult),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn build_for_player_with_tags(
+        self,
+        r#type: Type,
+        team: TeamId,
+        slot: SlotId,
+        tags: Vec<String>,
+    ) -> PrintJson {
+        let mut message = self.build_for_player(r#type, team, slot);
+
+        if let Some(additional_info) = &mut message.additional_info {
+            additional_info.tags = Some(tags);
+        }
+
+        message
+    }
+
+    pub fn build_for_player(self, r#type: Type, team: TeamId, slot: SlotId) -> PrintJson {
+        PrintJson {
+            data: self.parts,
+            additional_info: Some(AdditionalInfo {
+                r#type: Some(r#type),
+                team: Some(team.0 as u32),
+                slot: Some(slot.0 as u32),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn build_hint(self, hint: &Hint) -> PrintJson {
+        PrintJson {
+            data: self.parts,
+            additional_info: Some(AdditionalInfo {
+                r#type: Some(Type::Hint),
+                receiving: Some(hint.receiving_player),
+                item: Some(hint.network_item()),
+                found: Some(hint.found),
+                ..Default::default()
+            }),
+        }
+    }
 }
 
 impl Default for MessageBuilder {
@@ -241,3 +473,59 @@ impl Default for MessageBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEAM: TeamId = TeamId(0);
+    const SLOT: SlotId = SlotId(1);
+    const VERSION: NetworkVersion = NetworkVersion::new(0, 6, 1);
+
+    /// Renders the message like a client that knows slot 1 as `Player1`
+    fn text(message: &PrintJson) -> String {
+        message
+            .data
+            .iter()
+            .map(|part| match part {
+                JsonMessagePart::Text { text } => text.clone(),
+                JsonMessagePart::PlayerId { player_id } => format!("Player{}", player_id.0),
+                part => panic!("unexpected message part {part:?}"),
+            })
+            .collect()
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|&tag| tag.to_owned()).collect()
+    }
+
+    #[test]
+    fn join() {
+        let message = PrintJson::join(TEAM, SLOT, "Clique", VERSION, tags(&["AP", "DeathLink"]));
+
+        assert_eq!(
+            text(&message),
+            "Player1 (Team #1) playing Clique has joined. Client(0.6.1), ['AP', 'DeathLink']."
+        );
+    }
+
+    #[test]
+    fn part() {
+        let message = PrintJson::part(TEAM, SLOT, VERSION, tags(&[]));
+
+        assert_eq!(
+            text(&message),
+            "Player1 (Team #1) has left the game. Client(0.6.1), []."
+        );
+    }
+
+    #[test]
+    fn tags_changed() {
+        let message = PrintJson::tags_changed(TEAM, SLOT, &tags(&["AP"]), tags(&["AP", "it's"]));
+
+        assert_eq!(
+            text(&message),
+            r#"Player1 (Team #1) has changed tags from ['AP'] to ['AP', "it's"]."#
+        );
+    }
+}
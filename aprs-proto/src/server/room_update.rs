@@ -2,16 +2,58 @@ use fnv::FnvHashSet;
 use serde::{Deserialize, Serialize};
 
 use crate::primitives::LocationId;
+use crate::server::{NetworkPlayer, Permissions};
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct RoomUpdate {
     // TODO: implement more fields
-    #[serde(skip_serializing_if = "FnvHashSet::is_empty")]
+    #[serde(default, skip_serializing_if = "FnvHashSet::is_empty")]
     pub checked_locations: FnvHashSet<LocationId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_points: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<Vec<NetworkPlayer>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Permissions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_cost: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_check_points: Option<u32>,
 }
 
 impl RoomUpdate {
     pub fn checked_locations(checked_locations: FnvHashSet<LocationId>) -> Self {
-        Self { checked_locations }
+        Self {
+            checked_locations,
+            ..Self::default()
+        }
+    }
+
+    pub fn hint_points(hint_points: u32) -> Self {
+        Self {
+            hint_points: Some(hint_points),
+            ..Self::default()
+        }
+    }
+
+    pub fn players(players: Vec<NetworkPlayer>) -> Self {
+        Self {
+            players: Some(players),
+            ..Self::default()
+        }
+    }
+
+    pub fn options(permissions: Permissions, hint_cost: u8, location_check_points: u32) -> Self {
+        Self {
+            permissions: Some(permissions),
+            hint_cost: Some(hint_cost),
+            location_check_points: Some(location_check_points),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_hint_points(mut self, hint_points: u32) -> Self {
+        self.hint_points = Some(hint_points);
+        self
     }
 }
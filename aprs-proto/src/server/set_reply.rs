@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::primitives::SlotId;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SetReply {
     pub key: String,
     pub value: Value,
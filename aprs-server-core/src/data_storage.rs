@@ -1,19 +1,44 @@
 use std::borrow::Borrow;
 use std::hash::Hash;
+use std::{fmt, io};
 
 use aprs_proto::client::{Set, SetOperation};
 use aprs_value::Value;
-use eyre::{Result, bail};
+use eyre::{Report, Result, bail};
 use fnv::FnvHashMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use tracing::error;
 
 // TODO: find instances of HashMap/HashSet that don't use FxHasher
-#[derive(Deserialize, Serialize)]
-pub struct DataStorage(FnvHashMap<String, Value>);
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(from = "FnvHashMap<String, Value>")]
+pub struct DataStorage {
+    entries: FnvHashMap<String, Entry>,
+    total_size: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Entry {
+    value: Value,
+    /// The size of the JSON representation of `value` in bytes
+    size: usize,
+}
+
+/// Limits for the size of the JSON representation of stored values in bytes
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DataStorageLimits {
+    /// The maximum size of a single value
+    pub max_value_size: Option<usize>,
+    /// The maximum size of all values combined
+    pub max_total_size: Option<usize>,
+}
 
 impl DataStorage {
     pub fn new() -> Self {
-        Self(FnvHashMap::default())
+        Self {
+            entries: FnvHashMap::default(),
+            total_size: 0,
+        }
     }
 
     pub fn get_raw<K>(&self, key: &K) -> Option<&Value>
@@ -21,15 +46,53 @@ impl DataStorage {
         K: Hash + Eq + ?Sized,
         String: Borrow<K>,
     {
-        self.0.get(key)
+        self.entries.get(key).map(|entry| &entry.value)
     }
 
     pub fn set_raw(&mut self, key: String, value: Value) {
-        self.0.insert(key, value);
+        let size = json_size(&value).unwrap_or_else(|err| {
+            error!("BUG: failed to serialize data storage value of {key:?}: {err}");
+            0
+        });
+
+        self.insert(key, Entry { value, size });
     }
 
-    /// Returns `(original_value, new_value)`
-    pub fn set(&mut self, set: &Set) -> Result<(Value, Value)> {
+    /// Returns `(original_value, new_value)`.
+    /// The new value is only stored if it stays within `limits`.
+    pub fn set(
+        &mut self,
+        set: &Set,
+        limits: &DataStorageLimits,
+    ) -> Result<(Value, Value), SetError> {
+        let (original_value, new_value, size) = self.evaluate_sized(set, limits)?;
+        let entry = Entry {
+            value: new_value.clone(),
+            size,
+        };
+
+        self.insert(set.key.clone(), entry);
+
+        Ok((original_value, new_value))
+    }
+
+    /// Like [`DataStorage::set`], but without storing the new value
+    pub fn evaluate(
+        &self,
+        set: &Set,
+        limits: &DataStorageLimits,
+    ) -> Result<(Value, Value), SetError> {
+        let (original_value, new_value, _) = self.evaluate_sized(set, limits)?;
+
+        Ok((original_value, new_value))
+    }
+
+    /// Returns `(original_value, new_value, new_size)`
+    fn evaluate_sized(
+        &self,
+        set: &Set,
+        limits: &DataStorageLimits,
+    ) -> Result<(Value, Value, usize), SetError> {
         let Set {
             key,
             default: _,
@@ -37,12 +100,38 @@ impl DataStorage {
             operations: _,
         } = set;
 
-        let current_value = self.get_raw(key);
-        let (original_value, new_value) = evaluate_set(current_value, set)?;
+        let current_entry = self.entries.get(key);
+        let current_value = current_entry.map(|entry| &entry.value);
+        let current_size = current_entry.map_or(0, |entry| entry.size);
+        let (original_value, new_value) =
+            evaluate_set(current_value, set).map_err(SetError::Operation)?;
+        let size = json_size(&new_value).map_err(SetError::Operation)?;
+
+        if let Some(max_size) = limits.max_value_size
+            && size > max_size
+        {
+            return Err(SetError::ValueTooLarge { size, max_size });
+        }
 
-        self.set_raw(key.clone(), new_value.clone());
+        let total_size = self.total_size - current_size + size;
 
-        Ok((original_value, new_value))
+        // Shrinking values is always allowed, so a full storage can be cleaned up
+        if let Some(max_total_size) = limits.max_total_size
+            && total_size > max_total_size
+            && size > current_size
+        {
+            return Err(SetError::StorageFull { max_total_size });
+        }
+
+        Ok((original_value, new_value, size))
+    }
+
+    fn insert(&mut self, key: String, entry: Entry) {
+        self.total_size += entry.size;
+
+        if let Some(old_entry) = self.entries.insert(key, entry) {
+            self.total_size -= old_entry.size;
+        }
     }
 }
 
@@ -52,6 +141,79 @@ impl Default for DataStorage {
     }
 }
 
+impl From<FnvHashMap<String, Value>> for DataStorage {
+    fn from(values: FnvHashMap<String, Value>) -> Self {
+        let mut data_storage = Self::new();
+
+        for (key, value) in values {
+            data_storage.set_raw(key, value);
+        }
+
+        data_storage
+    }
+}
+
+impl Serialize for DataStorage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.entries.iter().map(|(key, entry)| (key, &entry.value)))
+    }
+}
+
+#[derive(Debug)]
+pub enum SetError {
+    /// An operation failed, e.g. because of mismatching types
+    Operation(Report),
+    /// The new value is larger than `max_value_size`
+    ValueTooLarge { size: usize, max_size: usize },
+    /// Storing the new value would exceed `max_total_size`
+    StorageFull { max_total_size: usize },
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetError::Operation(err) => write!(f, "{err}"),
+            SetError::ValueTooLarge { size, max_size } => {
+                write!(
+                    f,
+                    "value is {size} bytes, but at most {max_size} bytes are allowed"
+                )
+            }
+            SetError::StorageFull { max_total_size } => {
+                write!(f, "data storage is full ({max_total_size} bytes)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+/// Returns the size of the JSON representation of `value` in bytes
+fn json_size(value: &Value) -> Result<usize> {
+    let mut counter = ByteCounter(0);
+
+    serde_json::to_writer(&mut counter, value)?;
+
+    Ok(counter.0)
+}
+
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 pub fn evaluate_set(current_value: Option<&Value>, set: &Set) -> Result<(Value, Value)> {
     let Set {
         key: _,
@@ -64,7 +226,10 @@ pub fn evaluate_set(current_value: Option<&Value>, set: &Set) -> Result<(Value,
         .or(default.as_ref())
         .cloned()
         .unwrap_or_else(|| Value::int(0));
-    let mut new_value = original_value.clone();
+    // Operations like `update` and `pop` mutate containers in place.
+    // Working on a copy ensures stored values are never mutated,
+    // so `original_value` and values sent out earlier stay unchanged.
+    let mut new_value = original_value.deep_copy();
 
     for operation in operations {
         new_value = match evaluate_set_operation(new_value, operation) {
@@ -82,7 +247,6 @@ pub fn evaluate_set_operation(current: Value, operation: &SetOperation) -> Resul
     Ok(match operation {
         SetOperation::Default => current,
         SetOperation::Replace(value) => value.clone(),
-        // TODO: implement remaining set ops
         SetOperation::Add(value) => current.add(value)?,
         SetOperation::Mul(value) => current.mul(value)?,
         SetOperation::Pow(value) => current.pow(value)?,
@@ -93,12 +257,240 @@ pub fn evaluate_set_operation(current: Value, operation: &SetOperation) -> Resul
         SetOperation::Max(value) => current.max(value)?.clone(),
         SetOperation::And(value) => current.and(value)?,
         SetOperation::Or(value) => current.or(value)?,
-        // SetOperation::Xor(value) => current.xor(value),
-        // SetOperation::LeftShift(value) => current.left_shift(value),
-        // SetOperation::RightShift(value) => current.right_shift(value),
-        // SetOperation::Remove(value) => current.remove(value),
+        SetOperation::Xor(value) => current.xor(value)?,
+        SetOperation::LeftShift(value) => current.left_shift(value)?,
+        SetOperation::RightShift(value) => current.right_shift(value)?,
+        SetOperation::Remove(value) => current.remove(value).map(|_| current)?,
         SetOperation::Pop(value) => current.pop(value).map(|_| current)?,
         SetOperation::Update(value) => current.update(value).map(|_| current)?,
-        _ => bail!("TODO: implement SetOperation: {operation:?}"),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(key: &str, value: Value) -> Set {
+        Set {
+            key: key.into(),
+            default: None,
+            want_reply: false,
+            operations: vec![SetOperation::Replace(value)],
+        }
+    }
+
+    /// A string value with a JSON size of `size` bytes
+    fn value_of_size(size: usize) -> Value {
+        Value::str("x".repeat(size - 2))
+    }
+
+    fn limits(max_value_size: Option<usize>, max_total_size: Option<usize>) -> DataStorageLimits {
+        DataStorageLimits {
+            max_value_size,
+            max_total_size,
+        }
+    }
+
+    #[test]
+    fn total_size_tracks_inserts_and_replacements() {
+        let mut data_storage = DataStorage::new();
+        let no_limits = limits(None, None);
+
+        data_storage
+            .set(&replace("a", value_of_size(10)), &no_limits)
+            .unwrap();
+        data_storage.set_raw("b".into(), value_of_size(20));
+        assert_eq!(data_storage.total_size, 30);
+
+        data_storage
+            .set(&replace("a", value_of_size(4)), &no_limits)
+            .unwrap();
+        assert_eq!(data_storage.total_size, 24);
+
+        data_storage.set_raw("b".into(), Value::int(7));
+        assert_eq!(data_storage.total_size, 5);
+    }
+
+    #[test]
+    fn total_size_of_loaded_values() {
+        let values = FnvHashMap::from_iter([
+            ("a".to_owned(), value_of_size(10)),
+            ("b".to_owned(), Value::int(123)),
+        ]);
+        let data_storage = DataStorage::from(values);
+
+        assert_eq!(data_storage.total_size, 13);
+    }
+
+    #[test]
+    fn value_too_large() {
+        let mut data_storage = DataStorage::new();
+        let limits = limits(Some(10), None);
+
+        data_storage
+            .set(&replace("a", value_of_size(10)), &limits)
+            .unwrap();
+
+        let result = data_storage.set(&replace("a", value_of_size(11)), &limits);
+
+        assert!(matches!(
+            result,
+            Err(SetError::ValueTooLarge {
+                size: 11,
+                max_size: 10
+            })
+        ));
+        assert_eq!(data_storage.get_raw("a"), Some(&value_of_size(10)));
+        assert_eq!(data_storage.total_size, 10);
+    }
+
+    #[test]
+    fn storage_full() {
+        let mut data_storage = DataStorage::new();
+        let limits = limits(None, Some(30));
+
+        data_storage
+            .set(&replace("a", value_of_size(20)), &limits)
+            .unwrap();
+        data_storage
+            .set(&replace("b", value_of_size(10)), &limits)
+            .unwrap();
+
+        let result = data_storage.set(&replace("c", Value::int(1)), &limits);
+
+        assert!(matches!(
+            result,
+            Err(SetError::StorageFull { max_total_size: 30 })
+        ));
+        assert_eq!(data_storage.get_raw("c"), None);
+        assert_eq!(data_storage.total_size, 30);
+
+        // replacing a value with one of the same size keeps the storage at its limit
+        data_storage
+            .set(&replace("b", value_of_size(10)), &limits)
+            .unwrap();
+        assert_eq!(data_storage.total_size, 30);
+    }
+
+    #[test]
+    fn shrinking_is_always_allowed() {
+        let mut data_storage = DataStorage::new();
+
+        // e.g. after lowering the limit on restart
+        data_storage.set_raw("a".into(), value_of_size(50));
+        data_storage.set_raw("b".into(), value_of_size(50));
+
+        let limits = limits(None, Some(30));
+
+        data_storage
+            .set(&replace("a", value_of_size(40)), &limits)
+            .unwrap();
+        assert_eq!(data_storage.total_size, 90);
+
+        let result = data_storage.set(&replace("b", value_of_size(51)), &limits);
+
+        assert!(matches!(result, Err(SetError::StorageFull { .. })));
+        assert_eq!(data_storage.total_size, 90);
+    }
+
+    #[test]
+    fn failed_operations_keep_the_value() {
+        let mut data_storage = DataStorage::new();
+        let no_limits = limits(None, None);
+
+        data_storage.set_raw("a".into(), Value::str("text"));
+
+        let set = Set {
+            key: "a".into(),
+            default: None,
+            want_reply: false,
+            operations: vec![SetOperation::Floor],
+        };
+
+        assert!(matches!(
+            data_storage.set(&set, &no_limits),
+            Err(SetError::Operation(_))
+        ));
+        assert_eq!(data_storage.get_raw("a"), Some(&Value::str("text")));
+        assert_eq!(data_storage.total_size, 6);
+    }
+
+    #[test]
+    fn container_operations_leave_earlier_values_unchanged() {
+        let mut data_storage = DataStorage::new();
+        let no_limits = limits(None, None);
+        let json = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+
+        data_storage.set_raw("dict".into(), json(r#"{"a": 1, "b": 2}"#));
+        data_storage.set_raw("list".into(), json("[1, 2, 3]"));
+
+        let operations = [
+            ("dict", SetOperation::Update(json(r#"{"c": 3}"#))),
+            ("dict", SetOperation::Pop(Value::str("a"))),
+            ("list", SetOperation::Remove(Value::int(2))),
+            ("list", SetOperation::Pop(Value::int(0))),
+        ];
+
+        for (key, operation) in operations {
+            // e.g. a value sent out in a `Retrieved` reply
+            let earlier_value = data_storage.get_raw(key).unwrap().clone();
+            let expected_value = earlier_value.deep_copy();
+            let set = Set {
+                key: key.into(),
+                default: None,
+                want_reply: true,
+                operations: vec![operation],
+            };
+
+            let (original_value, new_value) = data_storage.set(&set, &no_limits).unwrap();
+
+            assert_eq!(earlier_value, expected_value);
+            assert_eq!(original_value, expected_value);
+            assert_ne!(new_value, expected_value);
+            assert_eq!(data_storage.get_raw(key), Some(&new_value));
+        }
+
+        assert_eq!(
+            data_storage.get_raw("dict"),
+            Some(&json(r#"{"b": 2, "c": 3}"#))
+        );
+        assert_eq!(data_storage.get_raw("list"), Some(&json("[3]")));
+    }
+
+    #[test]
+    fn huge_results_are_rejected() {
+        let mut data_storage = DataStorage::new();
+        let no_limits = limits(None, None);
+
+        data_storage.set_raw("int".into(), Value::int(3));
+        data_storage.set_raw("str".into(), Value::str("text"));
+
+        let operations = [
+            ("int", SetOperation::LeftShift(Value::int(u32::MAX))),
+            ("int", SetOperation::Pow(Value::int(u32::MAX))),
+            (
+                "int",
+                SetOperation::Mul(Value::int(1).left_shift(&Value::int(4095)).unwrap()),
+            ),
+            ("str", SetOperation::Mul(Value::int(u32::MAX))),
+        ];
+
+        for (key, operation) in operations {
+            let set = Set {
+                key: key.into(),
+                default: None,
+                want_reply: false,
+                operations: vec![operation],
+            };
+
+            assert!(matches!(
+                data_storage.set(&set, &no_limits),
+                Err(SetError::Operation(_))
+            ));
+        }
+
+        assert_eq!(data_storage.get_raw("int"), Some(&Value::int(3)));
+        assert_eq!(data_storage.get_raw("str"), Some(&Value::str("text")));
+        assert_eq!(data_storage.total_size, 7);
+    }
+}
@@ -4,4 +4,4 @@ mod bounce_matches;
 pub use bounce_matches::bounce_matches;
 
 mod data_storage;
-pub use data_storage::DataStorage;
+pub use data_storage::{DataStorage, DataStorageLimits, SetError};
@@ -11,4 +11,20 @@ pub struct Cli {
     pub bind_address: BindAddr,
     #[clap(long)]
     pub only_load: bool,
+    /// Read admin commands from stdin
+    #[clap(long)]
+    pub console: bool,
+    /// Maximum size of a single data storage value in bytes of JSON
+    #[clap(long)]
+    pub data_storage_max_value_size: Option<usize>,
+    /// Maximum size of all data storage values of a team combined in bytes of JSON
+    #[clap(long)]
+    pub data_storage_max_total_size: Option<usize>,
+    /// Data storage key prefix that only admins may write. Can be repeated.
+    #[clap(long = "data-storage-server-prefix")]
+    pub data_storage_server_prefixes: Vec<String>,
+    /// Data storage key prefix followed by a slot id that only that slot may write,
+    /// e.g. `slot_` for `slot_3_inventory`. Can be repeated.
+    #[clap(long = "data-storage-slot-prefix")]
+    pub data_storage_slot_prefixes: Vec<String>,
 }
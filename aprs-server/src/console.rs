@@ -0,0 +1,40 @@
+use std::io::{BufRead, stdin};
+use std::thread;
+
+use color_eyre::Result;
+use color_eyre::eyre::Context;
+use tracing::{debug, error};
+
+use crate::server::ServerHandle;
+
+/// Reads commands from stdin and feeds them into the server.
+///
+/// Stdin is read on its own thread, because a pending read can't be cancelled
+/// and would otherwise keep the runtime from shutting down.
+pub fn start(server_handle: ServerHandle) -> Result<()> {
+    thread::Builder::new()
+        .name("console".into())
+        .spawn(move || console_loop(server_handle))
+        .context("failed to spawn console thread")?;
+
+    Ok(())
+}
+
+fn console_loop(server_handle: ServerHandle) {
+    for line in stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Console: failed to read from stdin: {err:?}");
+                return;
+            }
+        };
+
+        if server_handle.blocking_console_command(line).is_err() {
+            debug!("Console: shutting down due to server shutdown");
+            return;
+        }
+    }
+
+    debug!("Console: stdin closed");
+}
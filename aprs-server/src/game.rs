@@ -7,6 +7,7 @@ use std::time::Instant;
 
 use aprs_proto::common::NetworkVersion;
 use aprs_proto::primitives::{ItemId, LocationId, SlotId, TeamId};
+use aprs_proto::server::{CommandPermission, Permissions, RemainingCommandPermission};
 use bitflags::bitflags;
 use byteorder::ReadBytesExt;
 use color_eyre::eyre::{Context, ContextCompat, Result, bail, ensure};
@@ -117,7 +118,7 @@ impl Game {
 #[serde(transparent)]
 pub struct SeedName(pub String);
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug)]
 #[serde(default)]
 pub struct ServerOptions {
     #[serde(rename = "password")]
@@ -127,10 +128,39 @@ pub struct ServerOptions {
     pub release_mode: ReleaseMode,
     pub remaining_mode: RemainingMode,
     pub collect_mode: CollectMode,
+    /// The percentage of a slot's locations a hint costs in hint points
+    pub hint_cost: u8,
+    /// The amount of hint points a slot receives per checked location
+    pub location_check_points: u32,
     #[serde(flatten)]
     pub rest: BTreeMap<String, serde_json::Value>,
 }
 
+impl ServerOptions {
+    pub fn permissions(&self) -> Permissions {
+        Permissions {
+            release: self.release_mode.into(),
+            collect: self.collect_mode.into(),
+            remaining: self.remaining_mode.into(),
+        }
+    }
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            client_password: None,
+            admin_password: None,
+            release_mode: ReleaseMode::default(),
+            remaining_mode: RemainingMode::default(),
+            collect_mode: CollectMode::default(),
+            hint_cost: 10,
+            location_check_points: 1,
+            rest: BTreeMap::new(),
+        }
+    }
+}
+
 #[derive(Deserialize_tuple, Debug, Hash, PartialEq, Eq, Copy, Clone)]
 pub struct LocationInfo {
     pub item: ItemId,
@@ -252,7 +282,7 @@ impl From<PickledVersion> for NetworkVersion {
     }
 }
 
-#[derive(Deserialize, Default, Debug)]
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum ReleaseMode {
     Disabled,
@@ -263,7 +293,7 @@ pub enum ReleaseMode {
     Goal,
 }
 
-#[derive(Deserialize, Default, Debug)]
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum CollectMode {
     Disabled,
@@ -274,7 +304,7 @@ pub enum CollectMode {
     Goal,
 }
 
-#[derive(Deserialize, Default, Debug)]
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum RemainingMode {
     Disabled,
@@ -282,3 +312,37 @@ pub enum RemainingMode {
     #[default]
     Goal,
 }
+
+impl From<ReleaseMode> for CommandPermission {
+    fn from(mode: ReleaseMode) -> Self {
+        match mode {
+            ReleaseMode::Disabled => CommandPermission::Disabled,
+            ReleaseMode::Enabled => CommandPermission::Enabled,
+            ReleaseMode::Auto => CommandPermission::Auto,
+            ReleaseMode::AutoEnabled => CommandPermission::AutoEnabled,
+            ReleaseMode::Goal => CommandPermission::Goal,
+        }
+    }
+}
+
+impl From<CollectMode> for CommandPermission {
+    fn from(mode: CollectMode) -> Self {
+        match mode {
+            CollectMode::Disabled => CommandPermission::Disabled,
+            CollectMode::Enabled => CommandPermission::Enabled,
+            CollectMode::Auto => CommandPermission::Auto,
+            CollectMode::AutoEnabled => CommandPermission::AutoEnabled,
+            CollectMode::Goal => CommandPermission::Goal,
+        }
+    }
+}
+
+impl From<RemainingMode> for RemainingCommandPermission {
+    fn from(mode: RemainingMode) -> Self {
+        match mode {
+            RemainingMode::Disabled => RemainingCommandPermission::Disabled,
+            RemainingMode::Enabled => RemainingCommandPermission::Enabled,
+            RemainingMode::Goal => RemainingCommandPermission::Goal,
+        }
+    }
+}
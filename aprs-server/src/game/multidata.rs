@@ -1,10 +1,10 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 use aprs_proto::common::NetworkVersion;
 use aprs_proto::primitives::ConnectName;
-use aprs_proto::server::NetworkSlot;
 use aprs_proto::server::print_json::HintStatus;
+use aprs_proto::server::{Hint, NetworkSlot};
 use aprs_value::{Dict, Str, Tuple, Value};
 use color_eyre::eyre::{Result, bail};
 use litemap::LiteMap;
@@ -14,7 +14,7 @@ use tracing::debug;
 
 use crate::game::{
     GameData, HashedGameData, ItemId, LocationId, LocationInfo, MinimumVersions, PickledVersion,
-    SeedName, ServerOptions, SlotId, TeamAndSlot,
+    SeedName, ServerOptions, SlotId, TeamAndSlot, TeamId,
 };
 
 #[serde_as]
@@ -34,15 +34,82 @@ pub struct MultiData {
     pub locations: BTreeMap<SlotId, LiteMap<LocationId, LocationInfo>>,
     pub spheres: Vec<BTreeMap<SlotId, Vec<LocationId>>>,
     pub precollected_items: BTreeMap<SlotId, Vec<ItemId>>,
+    #[serde(default)]
+    pub precollected_hints: BTreeMap<SlotId, Vec<Hint>>,
+    #[serde(default)]
+    pub er_hint_data: BTreeMap<SlotId, BTreeMap<LocationId, String>>,
+    #[serde(default)]
+    pub race_mode: u8,
     #[serde(flatten)]
     pub rest: BTreeMap<String, Value>,
 }
 
 impl MultiData {
+    /// A multiworld of a single team, where every player plays `game`, which has no locations
+    #[cfg(test)]
+    pub fn for_tests(slot_names: &[&str], game: &str) -> Self {
+        use aprs_proto::primitives::SlotName;
+        use aprs_proto::server::SlotType;
+
+        use crate::game::{SeedName, ServerOptions};
+
+        let slots = (1..).map(SlotId).zip(slot_names);
+        let slot_info = slots
+            .clone()
+            .map(|(slot, &name)| {
+                let slot_info = NetworkSlot {
+                    name: SlotName(name.into()),
+                    game: game.into(),
+                    r#type: SlotType::Player,
+                    group_members: serde_json::Value::Array(Vec::new()),
+                };
+
+                (slot, slot_info)
+            })
+            .collect();
+        let connect_names = slots
+            .clone()
+            .map(|(slot, &name)| {
+                let team_and_slot = TeamAndSlot {
+                    team: TeamId(0),
+                    slot,
+                };
+
+                (ConnectName(name.into()), team_and_slot)
+            })
+            .collect();
+
+        Self {
+            slot_info: Arc::new(slot_info),
+            slot_data: Arc::default(),
+            connect_names,
+            seed_name: SeedName("test".into()),
+            minimum_versions: MinimumVersions {
+                server: NetworkVersion::new(0, 0, 0),
+                clients: BTreeMap::new(),
+            },
+            server_options: ServerOptions::default(),
+            version: NetworkVersion::new(0, 6, 1),
+            data_package: Arc::default(),
+            locations: slots.map(|(slot, _)| (slot, LiteMap::new())).collect(),
+            spheres: Vec::new(),
+            precollected_items: BTreeMap::new(),
+            precollected_hints: BTreeMap::new(),
+            er_hint_data: BTreeMap::new(),
+            race_mode: 0,
+            rest: BTreeMap::new(),
+        }
+    }
+
     pub fn get_slot_id(&self, name: &str) -> Option<SlotId> {
         Some(self.connect_names.get(name)?.slot)
     }
 
+    /// Returns `true` if the seed was generated for a race
+    pub fn is_race(&self) -> bool {
+        self.race_mode != 0
+    }
+
     pub fn get_slot_info(&self, slot: SlotId) -> Option<&NetworkSlot> {
         self.slot_info.get(&slot)
     }
@@ -55,14 +122,51 @@ impl MultiData {
         Some(&self.data_package.get(game)?.game_data)
     }
 
+    /// Returns the name of `item_id` in the game of `slot`
+    pub fn item_name(&self, slot: SlotId, item_id: ItemId) -> Option<&str> {
+        let game = &self.get_slot_info(slot)?.game;
+
+        self.get_game_data(game)?
+            .item_name_to_id
+            .iter()
+            .find(|(_, id)| **id == item_id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the name of `location_id` in the game of `slot`
+    pub fn location_name(&self, slot: SlotId, location_id: LocationId) -> Option<&str> {
+        let game = &self.get_slot_info(slot)?.game;
+
+        self.get_game_data(game)?
+            .location_name_to_id
+            .iter()
+            .find(|(_, id)| **id == location_id)
+            .map(|(name, _)| name.as_str())
+    }
+
     pub fn location_info(&self, slot: SlotId, location_id: LocationId) -> Option<&LocationInfo> {
         self.locations.get(&slot)?.get(&location_id)
     }
 
+    pub fn entrance(&self, slot: SlotId, location_id: LocationId) -> Option<&str> {
+        self.er_hint_data
+            .get(&slot)?
+            .get(&location_id)
+            .map(String::as_str)
+    }
+
     pub fn slot_ids(&self) -> impl Iterator<Item = SlotId> {
         self.slot_info.keys().copied()
     }
 
+    pub fn team_ids(&self) -> impl Iterator<Item = TeamId> {
+        self.connect_names
+            .values()
+            .map(|team_and_slot| team_and_slot.team)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
     pub fn location_ids(&self, slot: SlotId) -> impl Iterator<Item = LocationId> {
         self.get_locations(slot)
             .into_iter()
@@ -70,20 +174,24 @@ impl MultiData {
             .copied()
     }
 
-    // TODO: return all found locations, not just the first one
     // TODO: create lookup map IF needed
-    pub fn find_item_location(&self, item_id: ItemId) -> Option<(SlotId, LocationId, u64)> {
-        for (slot, locations) in &self.locations {
-            for (location_id, location) in locations {
-                if location.item != item_id {
-                    continue;
-                }
-
-                return Some((*slot, *location_id, location.flags));
-            }
-        }
-
-        None
+    /// Returns all locations containing `item_id` for `receiving_slot`
+    /// as `(finding_slot, location_id, location_info)`.
+    pub fn find_item_locations(
+        &self,
+        receiving_slot: SlotId,
+        item_id: ItemId,
+    ) -> impl Iterator<Item = (SlotId, LocationId, &LocationInfo)> {
+        self.locations
+            .iter()
+            .flat_map(|(finding_slot, locations)| {
+                locations
+                    .iter()
+                    .map(move |(location_id, location)| (*finding_slot, *location_id, location))
+            })
+            .filter(move |(_, _, location)| {
+                location.item == item_id && location.slot == receiving_slot
+            })
     }
 }
 
@@ -3,6 +3,7 @@
 use std::hash::BuildHasherDefault;
 use std::time::Instant;
 
+use aprs_server_core::DataStorageLimits;
 use color_eyre::Result;
 use color_eyre::eyre::Context;
 use hashers::fx_hash::FxHasher;
@@ -16,6 +17,8 @@ use crate::server::{Config, Server};
 mod cli;
 pub use cli::Cli;
 
+mod console;
+
 pub mod game;
 pub mod net;
 pub mod server;
@@ -56,10 +59,23 @@ async fn start(game: Game, cli: Cli) -> Result<()> {
         .with_context(|| format!("failed to listen on {:?})", cli.bind_address))?;
 
     let state_path = cli.multiworld_path.with_extension("aprs.state");
-    let config = Config::new().with_state_path(state_path);
+    let data_storage_limits = DataStorageLimits {
+        max_value_size: cli.data_storage_max_value_size,
+        max_total_size: cli.data_storage_max_total_size,
+    };
+    let config = Config::new()
+        .with_state_path(state_path)
+        .with_console(cli.console)
+        .with_data_storage_limits(data_storage_limits)
+        .with_data_storage_server_prefixes(cli.data_storage_server_prefixes)
+        .with_data_storage_slot_prefixes(cli.data_storage_slot_prefixes);
     let server = Server::new(config, game.multi_data)?;
     let server_handle = server.handle();
 
+    if cli.console {
+        console::start(server_handle.clone())?;
+    }
+
     websocket::start(listener, server_handle);
 
     info!("Server started.");
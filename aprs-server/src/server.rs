@@ -1,21 +1,30 @@
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Instant;
 
-use aprs_proto::primitives::{SlotId, TeamId};
-use aprs_proto::server::NetworkPlayer;
-use aprs_value::Value;
+use aprs_proto::common::NetworkVersion;
+use aprs_proto::primitives::{LocationId, SlotId, TeamId};
+use aprs_proto::server::print_json::HintStatus;
+use aprs_proto::server::{Hint, InvalidPacket, NetworkItem, NetworkPlayer, PrintJson, SetReply};
+use aprs_value::{Dict, List, Value};
 use color_eyre::Result;
-use fnv::FnvHashMap;
+use color_eyre::eyre::bail;
+use fnv::{FnvHashMap, FnvHashSet};
+use itertools::Itertools;
+use smallvec::SmallVec;
 use tokio::sync::{Mutex, mpsc};
+use tokio::task::AbortHandle;
 use tracing::{debug, error, info, warn};
 
+use crate::FnvIndexMap;
 use crate::game::MultiData;
 use crate::server::control::ControlOrMessage;
+use crate::server::journal::Journal;
+use crate::server::persistence::Persistence;
 use crate::server::state::State;
 
 mod config;
-pub use config::Config;
+pub use config::{Config, KeyOwner};
 
 mod server_handle;
 pub use server_handle::ServerHandle;
@@ -33,9 +42,18 @@ pub mod control;
 
 pub type ServerMessage = aprs_proto::server::Message;
 pub type ClientMessage = aprs_proto::client::Message;
-pub type ClientMessages = aprs_proto::client::Messages;
+/// The messages of a single frame, each decoded independently
+pub type ClientMessages = SmallVec<[Result<ClientMessage, InvalidPacket>; 1]>;
 
+mod commands;
+pub use commands::PermissionLevel;
+
+mod admin_command_handlers;
+mod command_handlers;
+mod console_handlers;
 mod event_handlers;
+mod journal;
+mod persistence;
 mod state;
 
 pub struct Server {
@@ -47,11 +65,30 @@ pub struct Server {
     // TODO: definitely move away from ClientAddr and move to client ids
     clients: FnvHashMap<ClientId, Rc<Mutex<Client>>>,
     state: State,
+    persistence: Option<Persistence>,
+    countdown: Option<AbortHandle>,
 }
 
 impl Server {
+    /// The protocol version announced to clients
+    pub const VERSION: NetworkVersion = NetworkVersion::new(0, 6, 6);
+
     pub fn new(config: Config, multi_data: MultiData) -> Result<Self> {
+        let minimum_version = multi_data.minimum_versions.server;
+
+        if minimum_version > Self::VERSION {
+            bail!(
+                "the multiworld requires server version {minimum_version}, \
+                but this server only supports {}",
+                Self::VERSION
+            );
+        }
+
         let state = Self::load_state(&config, &multi_data)?;
+        let persistence = config
+            .state_path()
+            .map(|state_path| Persistence::start(state_path.to_owned(), state.clone()))
+            .transpose()?;
         let (client_message_sender, client_message_receiver) = mpsc::channel(10_000);
 
         Ok(Self {
@@ -61,6 +98,8 @@ impl Server {
             clients: FnvHashMap::default(),
             multi_data,
             state,
+            persistence,
+            countdown: None,
         })
     }
 
@@ -72,7 +111,7 @@ impl Server {
             return Ok(State::new(multi_data));
         };
 
-        Ok(match State::try_load(state_path)? {
+        let mut state = match State::try_load(state_path)? {
             Some(state) => {
                 info!("Loaded existing state from {:?}", state_path);
                 state
@@ -81,7 +120,16 @@ impl Server {
                 info!("No existing state found at {:?}", state_path);
                 State::new(multi_data)
             }
-        })
+        };
+
+        let journal_path = Journal::path_for(state_path);
+        let num_replayed = Journal::replay(&journal_path, &mut state)?;
+
+        if num_replayed > 0 {
+            info!("Replayed {num_replayed} change(s) from {journal_path:?}");
+        }
+
+        Ok(state)
     }
 
     pub async fn run(self) -> Result<()> {
@@ -94,28 +142,34 @@ impl Server {
         loop {
             let Some(event) = self.client_message_receiver.recv().await else {
                 debug!("Event channel closed.");
-                return;
+                break;
             };
 
             self.on_event(event).await;
+            self.persist_state();
+        }
+
+        if let Some(persistence) = self.persistence.take() {
+            persistence.shutdown().await;
         }
     }
 
-    fn get_key(&self, key: &str) -> Option<Value> {
+    /// Returns the value of `key` as seen by the requesting `slot`
+    fn get_key(&self, team: TeamId, slot: SlotId, key: &str) -> Option<Value> {
         if let Some(key) = key.strip_prefix("_read_") {
-            return self.get_special_key(key);
+            return self.get_special_key(slot, key);
         }
 
-        self.state.data_storage.get_raw(key).cloned()
+        self.state.get_data_storage_value(team, key).cloned()
     }
 
-    fn get_special_key(&self, key: &str) -> Option<Value> {
+    fn get_special_key(&self, slot: SlotId, key: &str) -> Option<Value> {
         if let Some(key) = key.strip_prefix("hints_") {
             return self.get_hints(key);
         }
 
         if let Some(key) = key.strip_prefix("slot_data_") {
-            return self.get_slot_data(key);
+            return self.get_slot_data(slot, key);
         }
 
         if let Some(key) = key.strip_prefix("item_name_groups_") {
@@ -139,24 +193,59 @@ impl Server {
         None
     }
 
-    fn get_item_name_groups(&self, _key: &str) -> Option<Value> {
-        warn!("TODO: implement get_item_name_groups");
-        None
+    fn get_item_name_groups(&self, game: &str) -> Option<Value> {
+        let game_data = self.multi_data.get_game_data(game)?;
+
+        Some(Self::name_groups_value(&game_data.item_name_groups))
     }
 
-    fn get_location_name_groups(&self, _key: &str) -> Option<Value> {
-        warn!("TODO: implement get_location_name_groups");
-        None
+    fn get_location_name_groups(&self, game: &str) -> Option<Value> {
+        let game_data = self.multi_data.get_game_data(game)?;
+
+        Some(Self::name_groups_value(&game_data.location_name_groups))
     }
 
-    fn get_client_status(&self, _key: &str) -> Option<Value> {
-        warn!("TODO: implement get_client_status");
-        None
+    fn name_groups_value(name_groups: &FnvIndexMap<String, Vec<String>>) -> Value {
+        let dict = Dict::new();
+
+        {
+            let mut dict = dict.write();
+
+            for (group, names) in name_groups {
+                let names = names.iter().map(|name| name.as_str()).collect::<List>();
+
+                dict.insert(group.as_str(), names)
+                    .expect("BUG: string keys are always hashable");
+            }
+        }
+
+        dict.into()
+    }
+
+    fn get_client_status(&self, key: &str) -> Option<Value> {
+        let (team, slot) = key.split_once("_")?;
+        let team = team.parse::<i64>().map(TeamId).ok()?;
+        let slot = slot.parse::<i64>().map(SlotId).ok()?;
+
+        Some(self.client_status_value(team, slot))
+    }
+
+    fn client_status_value(&self, team: TeamId, slot: SlotId) -> Value {
+        let client_status = self
+            .state
+            .get_slot_state(team, slot)
+            .map(|slot_state| slot_state.client_status())
+            .unwrap_or_default();
+
+        Value::int(client_status as u8)
+    }
+
+    fn client_status_key(team: TeamId, slot: SlotId) -> String {
+        format!("_read_client_status_{}_{}", team.0, slot.0)
     }
 
     fn get_race_mode(&self, _key: &str) -> Option<Value> {
-        warn!("TODO: implement get_race_mode");
-        None
+        Some(Value::int(self.multi_data.race_mode))
     }
 
     fn get_hints(&self, key: &str) -> Option<Value> {
@@ -164,43 +253,319 @@ impl Server {
         let team = team.parse::<i64>().map(TeamId).ok()?;
         let slot = slot.parse::<i64>().map(SlotId).ok()?;
 
-        let hints = self.state.get_hints(team, slot)?;
+        Some(self.hints_value(team, slot))
+    }
+
+    fn hints_value(&self, team: TeamId, slot: SlotId) -> Value {
+        let hints = self
+            .state
+            .get_hints(team, slot)
+            .iter()
+            .map(Value::from)
+            .collect::<List>();
+
+        Value::List(hints)
+    }
+
+    /// Returns the hint points a hint costs for the given slot
+    fn hint_cost(&self, slot: SlotId) -> u32 {
+        let hint_cost = u32::from(self.multi_data.server_options.hint_cost);
+
+        if hint_cost == 0 {
+            return 0;
+        }
+
+        let num_locations = self
+            .multi_data
+            .get_locations(slot)
+            .map(|locations| locations.len())
+            .unwrap_or_default() as u32;
+
+        (hint_cost * num_locations / 100).max(1)
+    }
+
+    /// Returns the hint points the given slot has available
+    fn hint_points(&self, team: TeamId, slot: SlotId) -> u32 {
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
+            return 0;
+        };
+
+        // `/option location_check_points` accepts any value
+        let earned_points = (slot_state.checked_locations().len() as u32)
+            .saturating_mul(self.multi_data.server_options.location_check_points);
+
+        earned_points.saturating_sub(slot_state.spent_hint_points())
+    }
+
+    fn goal_completed(&self, team: TeamId, slot: SlotId) -> bool {
+        self.state
+            .get_slot_state(team, slot)
+            .is_some_and(|slot_state| slot_state.goal_completed())
+    }
+
+    /// Returns the unchecked locations in other worlds that contain items for the given slot,
+    /// grouped by the slot owning the location
+    fn uncollected_locations(
+        &self,
+        team: TeamId,
+        slot: SlotId,
+    ) -> BTreeMap<SlotId, FnvHashSet<LocationId>> {
+        let mut uncollected_locations = BTreeMap::<SlotId, FnvHashSet<LocationId>>::new();
+
+        for item in self.remaining_items(team, slot) {
+            if item.player == slot {
+                continue;
+            }
+
+            uncollected_locations
+                .entry(item.player)
+                .or_default()
+                .insert(item.location);
+        }
+
+        uncollected_locations
+    }
+
+    /// Returns the items for the given slot that are still missing from any world
+    fn remaining_items(&self, team: TeamId, slot: SlotId) -> Vec<NetworkItem> {
+        let mut remaining_items = Vec::new();
+
+        for (&finding_slot, locations) in &self.multi_data.locations {
+            let Some(slot_state) = self.state.get_slot_state(team, finding_slot) else {
+                continue;
+            };
+
+            for (&location, location_info) in locations.iter() {
+                if location_info.slot == slot && slot_state.missing_locations().contains(&location)
+                {
+                    remaining_items.push(NetworkItem {
+                        item: location_info.item,
+                        location,
+                        player: finding_slot,
+                        flags: location_info.flags,
+                    });
+                }
+            }
+        }
+
+        remaining_items
+    }
+
+    /// Creates a hint for the given location.
+    /// The status of an existing hint is retained, otherwise `new_status` is used.
+    fn create_hint(
+        &self,
+        team: TeamId,
+        finding_slot: SlotId,
+        location: LocationId,
+        new_status: HintStatus,
+    ) -> Option<Hint> {
+        let location_info = self.multi_data.location_info(finding_slot, location)?;
+        let found = self
+            .state
+            .get_slot_state(team, finding_slot)
+            .is_some_and(|slot_state| slot_state.checked_locations().contains(&location));
+        let existing_hint = self.state.find_hint(team, finding_slot, location);
+        let status = match existing_hint {
+            _ if found => HintStatus::Found,
+            Some(hint) => hint.status,
+            None => new_status,
+        };
+        let entrance = self
+            .multi_data
+            .entrance(finding_slot, location)
+            .unwrap_or_default()
+            .to_owned();
+
+        Some(Hint {
+            receiving_player: location_info.slot,
+            finding_player: finding_slot,
+            location,
+            item: location_info.item,
+            found,
+            entrance,
+            item_flags: location_info.flags,
+            status,
+        })
+    }
 
-        Some(hints)
+    fn hints_key(team: TeamId, slot: SlotId) -> String {
+        format!("_read_hints_{}_{}", team.0, slot.0)
     }
 
-    fn get_slot_data(&self, key: &str) -> Option<Value> {
+    /// Adds or replaces the given hints and notifies clients watching the affected hint keys.
+    async fn update_hints(&mut self, team: TeamId, cause_slot: SlotId, hints: Vec<Hint>) {
+        if hints.is_empty() {
+            return;
+        }
+
+        let original_values = hints
+            .iter()
+            .flat_map(|hint| [hint.finding_player, hint.receiving_player])
+            .unique()
+            .map(|slot| (slot, self.hints_value(team, slot)))
+            .collect_vec();
+
+        for hint in hints {
+            self.state.add_hint(team, hint);
+        }
+
+        for (slot, original_value) in original_values {
+            self.notify_key_watchers(
+                team,
+                SetReply {
+                    key: Self::hints_key(team, slot),
+                    value: self.hints_value(team, slot),
+                    original_value,
+                    slot: cause_slot,
+                },
+            )
+            .await;
+        }
+    }
+
+    async fn send_hint_messages(&self, team: TeamId, hints: &[Hint]) {
+        for hint in hints {
+            let message = Arc::new(ServerMessage::PrintJson(PrintJson::hint(hint)));
+
+            self.print_to_console(Some(team), &message);
+
+            self.broadcast_slot(team, hint.receiving_player, message.clone())
+                .await;
+
+            if hint.finding_player != hint.receiving_player {
+                self.broadcast_slot(team, hint.finding_player, message)
+                    .await;
+            }
+        }
+    }
+
+    /// Sends the `SetReply` to the clients of the team that watch its key
+    async fn notify_key_watchers(&self, team: TeamId, set_reply: SetReply) {
+        let key = set_reply.key.clone();
+        let set_reply = Arc::new(ServerMessage::SetReply(set_reply));
+
+        for client in self.clients.values() {
+            let client = client.lock().await;
+
+            if client.team_id == team && client.wants_updates_for_keys.contains(key.as_str()) {
+                client.send(set_reply.clone()).await;
+            }
+        }
+    }
+
+    fn get_slot_data(&self, requesting_slot: SlotId, key: &str) -> Option<Value> {
         let slot = key.parse::<i64>().map(SlotId).ok()?;
 
+        // other slots' data may leak spoilers
+        if self.multi_data.is_race() && slot != requesting_slot {
+            return None;
+        }
+
         self.multi_data.slot_data.get(&slot).cloned()
     }
 
     fn network_players(&self) -> Vec<NetworkPlayer> {
         self.multi_data
-            .slot_info
-            .iter()
-            .map(|(slot_id, slot_info)| NetworkPlayer {
-                team: TeamId(0),
+            .team_ids()
+            .cartesian_product(self.multi_data.slot_info.iter().collect_vec())
+            .map(|(team, (slot_id, slot_info))| NetworkPlayer {
+                team,
                 slot: *slot_id,
-                // TODO: get alias from state/slot_state
-                alias: slot_info.name.as_str().into(),
+                alias: self.player_alias(team, *slot_id).into(),
                 name: slot_info.name.clone(),
             })
             .collect::<Vec<_>>()
     }
 
+    /// Returns the number of clients connected to the given slot
+    async fn connection_count(&self, team: TeamId, slot: SlotId) -> usize {
+        let mut connection_count = 0;
+
+        for client in self.clients.values() {
+            let client = client.lock().await;
+
+            if client.is_connected && client.team_id == team && client.slot_id == slot {
+                connection_count += 1;
+            }
+        }
+
+        connection_count
+    }
+
+    /// Finds a player by slot name or alias, ignoring case
+    fn find_player(&self, name: &str) -> Option<(TeamId, SlotId)> {
+        let by_slot_name = self
+            .multi_data
+            .connect_names
+            .iter()
+            .find(|(connect_name, _)| connect_name.eq_ignore_ascii_case(name))
+            .map(|(_, team_and_slot)| (team_and_slot.team, team_and_slot.slot));
+
+        by_slot_name.or_else(|| {
+            self.multi_data
+                .connect_names
+                .values()
+                .map(|team_and_slot| (team_and_slot.team, team_and_slot.slot))
+                .find(|(team, slot)| self.player_alias(*team, *slot).eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Returns the alias of the given player, falling back to the slot name
+    fn player_alias(&self, team: TeamId, slot: SlotId) -> &str {
+        let alias = self
+            .state
+            .get_slot_state(team, slot)
+            .and_then(|slot_state| slot_state.alias());
+
+        if let Some(alias) = alias {
+            return alias;
+        }
+
+        self.multi_data
+            .get_slot_info(slot)
+            .map(|slot_info| slot_info.name.as_str())
+            .unwrap_or("<unknown>")
+    }
+
     async fn broadcast(&self, message: impl Into<Arc<ServerMessage>>) {
         let message = message.into();
 
+        self.print_to_console(None, &message);
+
         for client in self.clients.values() {
             client.lock().await.send(message.clone()).await;
         }
     }
 
-    async fn broadcast_messages(&self, messages: &[Arc<ServerMessage>]) {
+    async fn broadcast_team(&self, team: TeamId, message: impl Into<Arc<ServerMessage>>) {
+        let message = message.into();
+
+        self.print_to_console(Some(team), &message);
+
+        for client in self.clients.values() {
+            let client = client.lock().await;
+
+            if client.team_id != team {
+                continue;
+            }
+
+            client.send(message.clone()).await;
+        }
+    }
+
+    async fn broadcast_team_messages(&self, team: TeamId, messages: &[Arc<ServerMessage>]) {
+        for message in messages {
+            self.print_to_console(Some(team), message);
+        }
+
         for client in self.clients.values() {
             let client = client.lock().await;
 
+            if client.team_id != team {
+                continue;
+            }
+
             // TODO: allow multiple messages to be sent as a single batch
             for message in messages {
                 client.send(message.clone()).await;
@@ -208,13 +573,18 @@ impl Server {
         }
     }
 
-    async fn broadcast_slot(&self, slot: SlotId, message: impl Into<Arc<ServerMessage>>) {
+    async fn broadcast_slot(
+        &self,
+        team: TeamId,
+        slot: SlotId,
+        message: impl Into<Arc<ServerMessage>>,
+    ) {
         let message = message.into();
 
         for client in self.clients.values() {
             let client = client.lock().await;
 
-            if client.slot_id != slot {
+            if client.team_id != team || client.slot_id != slot {
                 continue;
             }
 
@@ -229,9 +599,12 @@ impl Server {
     }
 
     async fn sync_items_to_client(&self, client: &Mutex<Client>) {
-        let slot = client.lock().await.slot_id;
-        let Some(slot_state) = self.state.get_slot_state(slot) else {
-            error!("BUG: trying to sync items to invalid slot {:?}", slot);
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
+            error!("BUG: trying to sync items to invalid slot {slot:?} of team {team:?}");
             return;
         };
         let slot_received_items = slot_state.received_items();
@@ -239,20 +612,16 @@ impl Server {
         client.lock().await.sync_items(slot_received_items).await
     }
 
-    fn save_state(&self) {
-        let Some(state_path) = self.config.state_path() else {
-            return;
-        };
+    /// Hands the state changes made since the last call over to be persisted
+    fn persist_state(&mut self) {
+        let mutations = self.state.take_mutations();
 
-        info!("Saving state...");
-        let start = Instant::now();
-        let result = self.state.save(state_path);
-        let elapsed = start.elapsed();
+        if mutations.is_empty() {
+            return;
+        }
 
-        if let Err(err) = result {
-            error!("Failed to save state after {elapsed:?}: {err:?}");
-        } else {
-            info!("Saved state successfuly after {elapsed:?}");
+        if let Some(persistence) = &self.persistence {
+            persistence.record(mutations);
         }
     }
 
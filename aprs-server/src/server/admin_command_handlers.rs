@@ -0,0 +1,226 @@
+use aprs_proto::primitives::{LocationId, SlotId};
+use aprs_proto::server::{NetworkItem, PrintJson, RoomUpdate};
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use serde::de::value::Error as ValueError;
+use tracing::error;
+
+use crate::game::{CollectMode, ReleaseMode, RemainingMode};
+use crate::server::commands::{ADMIN_COMMANDS, Args, Command, CommandKind};
+
+impl super::Server {
+    /// Runs an admin command like `/send <player> <item name>` and returns its result.
+    /// The leading `/` is optional.
+    pub(super) async fn run_admin_command(&mut self, input: &str) -> String {
+        let input = input.trim();
+        let input = input.strip_prefix('/').unwrap_or(input);
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        let Some(command) = Command::find(ADMIN_COMMANDS, name) else {
+            return format!("Unknown admin command '/{name}'. Use !help to list all commands.");
+        };
+
+        let Some(args) = command.parse_args(args) else {
+            return format!("Usage: {}", command.usage());
+        };
+
+        match command.kind {
+            CommandKind::AdminSend => self.on_admin_command_send(&args).await,
+            CommandKind::AdminRelease => self.on_admin_command_release(&args).await,
+            CommandKind::AdminCollect => self.on_admin_command_collect(&args).await,
+            CommandKind::AdminAlias => self.on_admin_command_alias(&args).await,
+            CommandKind::AdminKick => self.on_admin_command_kick(&args).await,
+            CommandKind::AdminHint => self.on_admin_command_hint(&args).await,
+            CommandKind::AdminOption => self.on_admin_command_option(&args).await,
+            CommandKind::AdminSave => self.on_admin_command_save().await,
+            kind => {
+                error!("BUG: player command {kind:?} in the admin command list");
+                String::new()
+            }
+        }
+    }
+
+    async fn on_admin_command_send(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+        let (item_name, item) = match self.find_item(slot, args.required("item name")) {
+            Ok((item_name, item)) => (item_name.to_owned(), item),
+            Err(message) => return message,
+        };
+        if self.state.get_slot_state(team, slot).is_none() {
+            error!("BUG: missing state for slot {slot:?}");
+            return unknown_player(player);
+        }
+
+        self.state.add_received_items(
+            team,
+            slot,
+            vec![NetworkItem {
+                item,
+                location: LocationId(-1),
+                player: SlotId::SERVER,
+                flags: 0,
+            }],
+        );
+
+        self.sync_items_to_clients().await;
+
+        let message = format!(
+            "Cheat console: sending \"{item_name}\" to {}.",
+            self.player_alias(team, slot)
+        );
+
+        self.broadcast_team(team, PrintJson::chat_message(message.clone()))
+            .await;
+
+        message
+    }
+
+    async fn on_admin_command_release(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+
+        self.release(team, slot).await;
+
+        format!("Released {}.", self.player_alias(team, slot))
+    }
+
+    async fn on_admin_command_collect(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+
+        self.collect(team, slot).await;
+
+        format!("Collected for {}.", self.player_alias(team, slot))
+    }
+
+    async fn on_admin_command_alias(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+        let alias = args.get("name").unwrap_or_default();
+
+        self.set_player_alias(team, slot, alias).await;
+
+        format!(
+            "{player} is now known as {}.",
+            self.player_alias(team, slot)
+        )
+    }
+
+    async fn on_admin_command_kick(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+        let mut num_kicked = 0;
+
+        for client in self.clients.values() {
+            let client = client.lock().await;
+
+            if client.team_id == team && client.slot_id == slot {
+                client.close().await;
+                num_kicked += 1;
+            }
+        }
+
+        format!(
+            "Kicked {num_kicked} connection(s) of {}.",
+            self.player_alias(team, slot)
+        )
+    }
+
+    async fn on_admin_command_hint(&mut self, args: &Args) -> String {
+        let player = args.required("player");
+        let Some((team, slot)) = self.find_player(player) else {
+            return unknown_player(player);
+        };
+        let hints = match self.find_item_hints(team, slot, args.required("item name")) {
+            Ok(hints) => hints,
+            Err(message) => return message,
+        };
+
+        self.update_hints(team, slot, hints.clone()).await;
+        self.send_hint_messages(team, &hints).await;
+
+        format!(
+            "Revealed {} location(s) for {}.",
+            hints.len(),
+            self.player_alias(team, slot)
+        )
+    }
+
+    async fn on_admin_command_option(&mut self, args: &Args) -> String {
+        let name = args.required("name");
+        let value = args.required("value");
+        let server_options = &mut self.multi_data.server_options;
+
+        let result = match name {
+            "hint_cost" => value
+                .parse()
+                .map(|hint_cost| server_options.hint_cost = hint_cost)
+                .map_err(|err| err.to_string()),
+            "location_check_points" => value
+                .parse()
+                .map(|points| server_options.location_check_points = points)
+                .map_err(|err| err.to_string()),
+            "release_mode" => {
+                parse_mode::<ReleaseMode>(value).map(|mode| server_options.release_mode = mode)
+            }
+            "collect_mode" => {
+                parse_mode::<CollectMode>(value).map(|mode| server_options.collect_mode = mode)
+            }
+            "remaining_mode" => {
+                parse_mode::<RemainingMode>(value).map(|mode| server_options.remaining_mode = mode)
+            }
+            _ => {
+                return format!(
+                    "Unknown option '{name}'. Available options: hint_cost, location_check_points, \
+                    release_mode, collect_mode, remaining_mode"
+                );
+            }
+        };
+
+        if let Err(err) = result {
+            return format!("Invalid value '{value}' for option '{name}': {err}");
+        }
+
+        let room_update = RoomUpdate::options(
+            server_options.permissions(),
+            server_options.hint_cost,
+            server_options.location_check_points,
+        );
+
+        self.broadcast(room_update).await;
+
+        format!("Option '{name}' set to '{value}' until the server restarts.")
+    }
+
+    async fn on_admin_command_save(&mut self) -> String {
+        self.persist_state();
+
+        let Some(persistence) = &self.persistence else {
+            return "Saving is disabled on this server.".into();
+        };
+
+        persistence.snapshot().await;
+
+        "Saved the server state.".into()
+    }
+}
+
+fn unknown_player(player: &str) -> String {
+    format!("Unknown player '{player}'.")
+}
+
+fn parse_mode<'de, T: Deserialize<'de>>(value: &'de str) -> Result<T, String> {
+    T::deserialize(IntoDeserializer::<ValueError>::into_deserializer(value))
+        .map_err(|err| err.to_string())
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use aprs_proto as proto;
 use aprs_proto::client::ItemsHandling;
+use aprs_proto::common::NetworkVersion;
 use aprs_proto::primitives::{ConnectName, ItemId, SlotId, SlotName, TeamId};
 use aprs_proto::server::NetworkItem;
 use aprs_proto::server::ReceivedItems;
@@ -14,7 +15,9 @@ use tracing::{error, info};
 use crate::net::ClientAddr;
 use crate::server::client_id::ClientId;
 use crate::server::control::{Close, Control, ControlOrMessage};
-use crate::server::{Event, Server, ServerMessage, ServerMessageSender, ServerToClientConnection};
+use crate::server::{
+    Event, PermissionLevel, Server, ServerMessage, ServerMessageSender, ServerToClientConnection,
+};
 
 #[derive(Clone)]
 pub(super) struct Client {
@@ -28,7 +31,9 @@ pub(super) struct Client {
     pub team_id: TeamId,
     pub tags: FnvHashSet<String>,
     pub game: String,
+    pub version: NetworkVersion,
     pub wants_updates_for_keys: FnvHashSet<Str>,
+    pub permission_level: PermissionLevel,
     starting_inventory: FnvHashSet<ItemId>,
     items_handling: ItemsHandling,
     next_slot_item_index: usize,
@@ -78,7 +83,9 @@ impl Client {
             team_id: TeamId(-1),
             game: "<unknown>".into(),
             tags: FnvHashSet::default(),
+            version: NetworkVersion::new(0, 0, 0),
             wants_updates_for_keys: FnvHashSet::default(),
+            permission_level: PermissionLevel::Player,
             starting_inventory: FnvHashSet::default(),
             items_handling: ItemsHandling::empty(),
             next_slot_item_index: 0,
@@ -106,6 +113,11 @@ impl Client {
         self.send_control(Close).await
     }
 
+    /// The tags sorted and without duplicates, the way they are shown in chat
+    pub fn sorted_tags(&self) -> Vec<String> {
+        self.tags.iter().sorted().cloned().collect_vec()
+    }
+
     pub fn set_items_handling(&mut self, new_items_handling: proto::client::ItemsHandling) {
         if new_items_handling == self.items_handling {
             return;
@@ -0,0 +1,618 @@
+use std::time::Duration;
+
+use aprs_proto::primitives::{ItemId, SlotId, TeamId};
+use aprs_proto::server::print_json::HintStatus;
+use aprs_proto::server::{
+    CommandPermission, Hint, PrintJson, RemainingCommandPermission, RoomUpdate,
+};
+use itertools::Itertools;
+use levenshtein::levenshtein;
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::game::ItemClassification;
+use crate::server::PermissionLevel;
+use crate::server::client::Client;
+use crate::server::commands::{ADMIN_COMMANDS, Args, COMMANDS, Command, CommandKind};
+use crate::server::event::Event;
+
+const DEFAULT_COUNTDOWN_SECONDS: u32 = 10;
+/// Longer countdowns are shortened to this, so a typo doesn't keep the chat busy for hours
+const MAX_COUNTDOWN_SECONDS: u32 = 60;
+
+impl super::Server {
+    /// Runs a chat command. `input` is the chat message without the leading `!`.
+    pub(super) async fn on_command(&mut self, client: &Mutex<Client>, input: &str) {
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        let Some(command) = Command::find(COMMANDS, name) else {
+            reply(
+                client,
+                format!("Unknown command '!{name}'. Use !help to list all commands."),
+            )
+            .await;
+            return;
+        };
+
+        let permission_level = client.lock().await.permission_level;
+
+        if command.permission > permission_level {
+            reply(
+                client,
+                format!("You are not allowed to use !{}.", command.name),
+            )
+            .await;
+            return;
+        }
+
+        let Some(args) = command.parse_args(args) else {
+            reply(client, format!("Usage: {}", command.usage())).await;
+            return;
+        };
+
+        match command.kind {
+            CommandKind::Help => self.on_command_help(client).await,
+            CommandKind::Players => self.on_command_players(client).await,
+            CommandKind::Status => self.on_command_status(client).await,
+            CommandKind::Missing => self.on_command_missing(client).await,
+            CommandKind::Checked => self.on_command_checked(client).await,
+            CommandKind::Countdown => self.on_command_countdown(client, command, &args).await,
+            CommandKind::Hint => {
+                let item_name = args.get("item name").unwrap_or_default();
+                self.on_command_hint(client, item_name).await
+            }
+            CommandKind::Release => self.on_command_release(client).await,
+            CommandKind::Collect => self.on_command_collect(client).await,
+            CommandKind::Remaining => self.on_command_remaining(client).await,
+            CommandKind::Alias => {
+                let alias = args.get("name").unwrap_or_default();
+                self.on_command_alias(client, alias).await
+            }
+            CommandKind::Admin => {
+                let command = args.get("command").unwrap_or_default();
+                self.on_command_admin(client, command).await
+            }
+            CommandKind::AdminSend
+            | CommandKind::AdminRelease
+            | CommandKind::AdminCollect
+            | CommandKind::AdminAlias
+            | CommandKind::AdminKick
+            | CommandKind::AdminHint
+            | CommandKind::AdminOption
+            | CommandKind::AdminSave => {
+                error!(
+                    "BUG: admin command {:?} in the player command list",
+                    command.kind
+                );
+            }
+        }
+    }
+
+    async fn on_command_help(&mut self, client: &Mutex<Client>) {
+        let permission_level = client.lock().await.permission_level;
+        let mut help = String::from("Available commands:");
+
+        for command in COMMANDS.iter().chain(ADMIN_COMMANDS) {
+            if command.permission > permission_level {
+                continue;
+            }
+
+            help.push_str(&format!("\n{}: {}", command.usage(), command.help));
+        }
+
+        reply(client, help).await;
+    }
+
+    async fn on_command_admin(&mut self, client: &Mutex<Client>, input: &str) {
+        let permission_level = client.lock().await.permission_level;
+
+        let (subcommand, password) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        if subcommand == "login" {
+            let Some(admin_password) = &self.multi_data.server_options.admin_password else {
+                reply_admin(client, "No admin password is set on this server.").await;
+                return;
+            };
+
+            if password.trim() != admin_password {
+                reply_admin(client, "Invalid password.").await;
+                return;
+            }
+
+            client.lock().await.permission_level = PermissionLevel::Admin;
+            reply_admin(
+                client,
+                "Login successful. You can now use admin commands via `!admin /<command>`.",
+            )
+            .await;
+            return;
+        }
+
+        if permission_level < PermissionLevel::Admin {
+            reply_admin(
+                client,
+                "You must log in first using `!admin login <password>`.",
+            )
+            .await;
+            return;
+        }
+
+        let result = self.run_admin_command(input).await;
+
+        reply_admin(client, result).await;
+    }
+
+    async fn on_command_players(&mut self, client: &Mutex<Client>) {
+        let team = client.lock().await.team_id;
+        let mut connected_players = Vec::new();
+
+        for slot in self.multi_data.slot_ids() {
+            if self.connection_count(team, slot).await > 0 {
+                connected_players.push(self.player_alias(team, slot));
+            }
+        }
+
+        let num_players = self.multi_data.slot_ids().count();
+
+        reply(
+            client,
+            format!(
+                "Connected players ({} of {num_players}): {}",
+                connected_players.len(),
+                connected_players.iter().join(", ")
+            ),
+        )
+        .await;
+    }
+
+    async fn on_command_status(&mut self, client: &Mutex<Client>) {
+        let team = client.lock().await.team_id;
+        let mut status = String::from("Player status:");
+
+        for (slot, slot_info) in self.multi_data.slot_info.iter() {
+            let Some(slot_state) = self.state.get_slot_state(team, *slot) else {
+                error!("BUG: missing state for slot {slot:?}");
+                continue;
+            };
+            let num_checked = slot_state.checked_locations().len();
+            let num_locations = num_checked + slot_state.missing_locations().len();
+            let connection_count = self.connection_count(team, *slot).await;
+
+            status.push_str(&format!(
+                "\n{} ({}): {}, {num_checked}/{num_locations} locations checked, {connection_count} connection(s)",
+                self.player_alias(team, *slot),
+                slot_info.game,
+                slot_state.client_status().name(),
+            ));
+        }
+
+        reply(client, status).await;
+    }
+
+    async fn on_command_missing(&mut self, client: &Mutex<Client>) {
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
+            error!("BUG: missing state for slot {slot:?}");
+            return;
+        };
+
+        let mut message = PrintJson::builder().with_text("Missing locations:");
+
+        for location in slot_state.missing_locations().iter().sorted() {
+            message.add_text("\n");
+            message.add_location(slot, *location);
+        }
+
+        message.add_text(format!(
+            "\nFound {} missing location checks.",
+            slot_state.missing_locations().len()
+        ));
+
+        client
+            .lock()
+            .await
+            .send(message.build_command_result())
+            .await;
+    }
+
+    async fn on_command_checked(&mut self, client: &Mutex<Client>) {
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
+            error!("BUG: missing state for slot {slot:?}");
+            return;
+        };
+
+        let mut message = PrintJson::builder().with_text("Checked locations:");
+
+        for location in slot_state.checked_locations().iter().sorted() {
+            message.add_text("\n");
+            message.add_location(slot, *location);
+        }
+
+        message.add_text(format!(
+            "\nFound {} done location checks.",
+            slot_state.checked_locations().len()
+        ));
+
+        client
+            .lock()
+            .await
+            .send(message.build_command_result())
+            .await;
+    }
+
+    async fn on_command_countdown(
+        &mut self,
+        client: &Mutex<Client>,
+        command: &Command,
+        args: &Args,
+    ) {
+        let Some(seconds) = countdown_seconds(args.get("seconds")) else {
+            reply(client, format!("Usage: {}", command.usage())).await;
+            return;
+        };
+
+        // a new countdown replaces the running one
+        if let Some(countdown) = self.countdown.take() {
+            countdown.abort();
+        }
+
+        self.broadcast(PrintJson::countdown(
+            format!("[Server]: Starting countdown of {seconds}s"),
+            0,
+        ))
+        .await;
+
+        let event_sender = self.client_message_sender.clone();
+        let countdown = tokio::spawn(async move {
+            for remaining in (1..=seconds).rev() {
+                let message = PrintJson::countdown(format!("[Server]: {remaining}"), remaining);
+
+                if event_sender
+                    .send(Event::Broadcast(message.into()))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+
+            let message = PrintJson::countdown("[Server]: GO", 0);
+            event_sender
+                .send(Event::Broadcast(message.into()))
+                .await
+                .ok();
+        });
+
+        self.countdown = Some(countdown.abort_handle());
+    }
+
+    async fn on_command_release(&mut self, client: &Mutex<Client>) {
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let release_permission =
+            CommandPermission::from(self.multi_data.server_options.release_mode);
+
+        if !release_permission.allows_manual_use(self.goal_completed(team, slot)) {
+            let reason = match release_permission {
+                CommandPermission::Disabled => "has been disabled on this server",
+                _ => "requires you to have beaten the game on this server",
+            };
+
+            reply(client, format!("Sorry, client item releasing {reason}.")).await;
+            return;
+        }
+
+        self.release(team, slot).await;
+    }
+
+    async fn on_command_collect(&mut self, client: &Mutex<Client>) {
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let collect_permission =
+            CommandPermission::from(self.multi_data.server_options.collect_mode);
+
+        if !collect_permission.allows_manual_use(self.goal_completed(team, slot)) {
+            let reason = match collect_permission {
+                CommandPermission::Disabled => "has been disabled on this server",
+                _ => "requires you to have beaten the game on this server",
+            };
+
+            reply(client, format!("Sorry, client collecting {reason}.")).await;
+            return;
+        }
+
+        self.collect(team, slot).await;
+    }
+
+    async fn on_command_remaining(&mut self, client: &Mutex<Client>) {
+        let client = client.lock().await;
+        let (slot, team) = (client.slot_id, client.team_id);
+        let remaining_permission =
+            RemainingCommandPermission::from(self.multi_data.server_options.remaining_mode);
+
+        if !remaining_permission.allows_manual_use(self.goal_completed(team, slot)) {
+            let reason = match remaining_permission {
+                RemainingCommandPermission::Disabled => "has been disabled on this server",
+                _ => "requires you to have beaten the game on this server",
+            };
+
+            client
+                .send(PrintJson::command_result(format!(
+                    "Sorry, !remaining {reason}."
+                )))
+                .await;
+            return;
+        }
+
+        let remaining_items = self.remaining_items(team, slot);
+
+        if remaining_items.is_empty() {
+            client
+                .send(PrintJson::command_result("No remaining items found."))
+                .await;
+            return;
+        }
+
+        let mut message = PrintJson::builder().with_text("Remaining items: ");
+
+        for (i, item) in remaining_items.into_iter().enumerate() {
+            if i > 0 {
+                message.add_text(", ");
+            }
+
+            message.add_item(slot, item.item, item.flags);
+        }
+
+        client.send(message.build_command_result()).await;
+    }
+
+    async fn on_command_alias(&mut self, client: &Mutex<Client>, alias: &str) {
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+
+        self.set_player_alias(team, slot, alias).await;
+    }
+
+    /// Sets or, if `alias` is empty, resets the alias of a player
+    pub(super) async fn set_player_alias(&mut self, team: TeamId, slot: SlotId, alias: &str) {
+        let alias = alias.trim().chars().take(16).collect::<String>();
+        let alias = alias.trim();
+        let old_alias = self.player_alias(team, slot).to_owned();
+        if self.state.get_slot_state(team, slot).is_none() {
+            error!("BUG: missing state for slot {slot:?}");
+            return;
+        }
+
+        let message = if alias.is_empty() {
+            self.state.set_alias(team, slot, None);
+            format!(
+                "{old_alias} is now known as {}.",
+                self.player_alias(team, slot)
+            )
+        } else {
+            self.state.set_alias(team, slot, Some(alias.to_owned()));
+            format!("{old_alias} is now known as {alias}.")
+        };
+
+        self.broadcast(RoomUpdate::players(self.network_players()))
+            .await;
+        self.broadcast_team(team, PrintJson::chat_message(message))
+            .await;
+    }
+
+    async fn on_command_hint(&mut self, client: &Mutex<Client>, needle_item: &str) {
+        let needle_item = needle_item.trim();
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+
+        if needle_item.is_empty() {
+            let hint_cost = self.hint_cost(slot);
+            let hint_points = self.hint_points(team, slot);
+
+            reply(
+                client,
+                format!(
+                    "Usage: !hint <item name>. A hint costs {hint_cost} points. You have {hint_points} points."
+                ),
+            )
+            .await;
+            return;
+        }
+
+        let hints = match self.find_item_hints(team, slot, needle_item) {
+            Ok(hints) => hints,
+            Err(message) => {
+                reply(client, message).await;
+                return;
+            }
+        };
+
+        // only new hints for items that still need to be found cost points
+        let has_new_unfound_hints = hints.iter().any(|hint| {
+            !hint.found
+                && self
+                    .state
+                    .find_hint(team, hint.finding_player, hint.location)
+                    .is_none()
+        });
+
+        if has_new_unfound_hints {
+            let hint_cost = self.hint_cost(slot);
+            let hint_points = self.hint_points(team, slot);
+
+            if hint_points < hint_cost {
+                reply(
+                    client,
+                    format!(
+                        "You can't afford the hint. You have {hint_points} points and need at least {hint_cost}."
+                    ),
+                )
+                .await;
+                return;
+            }
+
+            if self.state.get_slot_state(team, slot).is_none() {
+                error!("BUG: missing state for slot {slot:?}");
+                return;
+            }
+
+            self.state.spend_hint_points(team, slot, hint_cost);
+        }
+
+        self.update_hints(team, slot, hints.clone()).await;
+        self.send_hint_messages(team, &hints).await;
+        self.broadcast_slot(
+            team,
+            slot,
+            RoomUpdate::hint_points(self.hint_points(team, slot)),
+        )
+        .await;
+    }
+
+    /// Finds the item of the slot's game whose name matches `needle_item` best.
+    /// The error contains a message for the player.
+    pub(super) fn find_item(
+        &self,
+        slot: SlotId,
+        needle_item: &str,
+    ) -> Result<(&str, ItemId), String> {
+        let Some(slot_info) = self.multi_data.get_slot_info(slot) else {
+            error!("BUG: tried to get slot_info for invalid slot {slot:?}");
+            return Err(format!("Unknown slot {}.", slot.0));
+        };
+        let game = &slot_info.game;
+        let Some(game_data) = self.multi_data.get_game_data(game) else {
+            error!("BUG: tried to get game data for invalid game {game:?}");
+            return Err(format!("Unknown game '{game}'."));
+        };
+
+        let Some((found_item_name, found_item_id, confidence)) = game_data
+            .item_name_to_id
+            .iter()
+            .map(|(item_name, item_id)| {
+                let distance = levenshtein(needle_item, item_name);
+                let confidence =
+                    (distance.min(needle_item.len()) as f32) / (needle_item.len() as f32);
+                let confidence = 1.0 - confidence;
+                let confidence = (confidence * 100.).floor() as u32;
+
+                (item_name, item_id, confidence)
+            })
+            .max_by_key(|(_, _, confidence)| *confidence)
+        else {
+            error!("BUG: world doesn't seem to have any items?");
+            return Err(format!("'{game}' doesn't have any items."));
+        };
+
+        let confidence_threshold = 70;
+
+        if confidence < confidence_threshold {
+            return Err(format!(
+                "No matching item found. Did you mean '{found_item_name}'? ({confidence}% match)"
+            ));
+        }
+
+        Ok((found_item_name, *found_item_id))
+    }
+
+    /// Creates hints for all locations that contain the item best matching `needle_item`.
+    /// The error contains a message for the player.
+    pub(super) fn find_item_hints(
+        &self,
+        team: TeamId,
+        slot: SlotId,
+        needle_item: &str,
+    ) -> Result<Vec<Hint>, String> {
+        let (found_item_name, found_item_id) = self.find_item(slot, needle_item)?;
+
+        let hints = self
+            .multi_data
+            .find_item_locations(slot, found_item_id)
+            // hinting other worlds may leak spoilers
+            .filter(|(finding_slot, _, _)| !self.multi_data.is_race() || *finding_slot == slot)
+            .filter_map(|(finding_slot, location, location_info)| {
+                let item_classification =
+                    ItemClassification::from_bits_retain(location_info.flags as u32);
+                let new_status = if item_classification.is_trap() {
+                    HintStatus::Avoid
+                } else {
+                    HintStatus::Priority
+                };
+
+                self.create_hint(team, finding_slot, location, new_status)
+            })
+            .collect_vec();
+
+        if hints.is_empty() {
+            return Err(format!("No location contains '{found_item_name}'."));
+        }
+
+        Ok(hints)
+    }
+}
+
+/// Sends the result of a command to the invoking client only
+async fn reply(client: &Mutex<Client>, text: impl Into<String>) {
+    client
+        .lock()
+        .await
+        .send(PrintJson::command_result(text))
+        .await;
+}
+
+/// Sends the result of an admin command to the invoking client only
+async fn reply_admin(client: &Mutex<Client>, text: impl Into<String>) {
+    client
+        .lock()
+        .await
+        .send(PrintJson::admin_command_result(text))
+        .await;
+}
+
+/// Returns the length of a countdown for the `seconds` argument, or `None` if it is invalid
+fn countdown_seconds(seconds: Option<&str>) -> Option<u32> {
+    let Some(seconds) = seconds else {
+        return Some(DEFAULT_COUNTDOWN_SECONDS);
+    };
+
+    seconds
+        .parse::<u32>()
+        .ok()
+        .map(|seconds| seconds.min(MAX_COUNTDOWN_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_seconds_are_clamped() {
+        assert_eq!(countdown_seconds(None), Some(DEFAULT_COUNTDOWN_SECONDS));
+        assert_eq!(countdown_seconds(Some("0")), Some(0));
+        assert_eq!(countdown_seconds(Some("60")), Some(60));
+        assert_eq!(countdown_seconds(Some("61")), Some(MAX_COUNTDOWN_SECONDS));
+        assert_eq!(
+            countdown_seconds(Some("4294967295")),
+            Some(MAX_COUNTDOWN_SECONDS)
+        );
+        assert_eq!(countdown_seconds(Some("-1")), None);
+        assert_eq!(countdown_seconds(Some("ten")), None);
+    }
+}
@@ -0,0 +1,407 @@
+use std::fmt::Write;
+
+/// Who is allowed to run a command.
+/// Levels are ordered, i.e. a higher level may run all commands of the lower levels.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Debug)]
+pub enum PermissionLevel {
+    #[default]
+    Player,
+    Admin,
+}
+
+pub(super) struct Command {
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    pub help: &'static str,
+    pub permission: PermissionLevel,
+    pub kind: CommandKind,
+}
+
+impl PermissionLevel {
+    /// The prefix commands of this level are invoked with
+    fn prefix(&self) -> char {
+        match self {
+            PermissionLevel::Player => '!',
+            PermissionLevel::Admin => '/',
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(super) enum CommandKind {
+    Help,
+    Players,
+    Status,
+    Missing,
+    Checked,
+    Countdown,
+    Hint,
+    Release,
+    Collect,
+    Remaining,
+    Alias,
+    Admin,
+    AdminSend,
+    AdminRelease,
+    AdminCollect,
+    AdminAlias,
+    AdminKick,
+    AdminHint,
+    AdminOption,
+    AdminSave,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(super) enum Arg {
+    /// A single word, or text in double quotes, which may contain spaces
+    Required(&'static str),
+    /// Like [`Arg::Required`], but may be omitted
+    Optional(&'static str),
+    /// The remaining text, which may contain spaces
+    Rest(&'static str),
+    /// The remaining text, which may contain spaces or be empty
+    OptionalRest(&'static str),
+}
+
+impl Arg {
+    fn name(&self) -> &'static str {
+        match *self {
+            Arg::Required(name)
+            | Arg::Optional(name)
+            | Arg::Rest(name)
+            | Arg::OptionalRest(name) => name,
+        }
+    }
+}
+
+pub(super) const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        args: &[],
+        help: "Lists all available commands.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Help,
+    },
+    Command {
+        name: "players",
+        args: &[],
+        help: "Lists the players of your team and whether they are connected.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Players,
+    },
+    Command {
+        name: "status",
+        args: &[],
+        help: "Shows the status and progress of every player in your team.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Status,
+    },
+    Command {
+        name: "missing",
+        args: &[],
+        help: "Lists the locations of your world that have not been checked yet.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Missing,
+    },
+    Command {
+        name: "checked",
+        args: &[],
+        help: "Lists the locations of your world that have been checked.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Checked,
+    },
+    Command {
+        name: "countdown",
+        args: &[Arg::Optional("seconds")],
+        help: "Starts a countdown for all players. Defaults to 10 seconds, at most 60 seconds.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Countdown,
+    },
+    Command {
+        name: "hint",
+        args: &[Arg::OptionalRest("item name")],
+        help: "Reveals the location of an item in exchange for hint points. \
+            Shows your hint points if no item is given.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Hint,
+    },
+    Command {
+        name: "release",
+        args: &[],
+        help: "Sends out all remaining items from your world.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Release,
+    },
+    Command {
+        name: "collect",
+        args: &[],
+        help: "Sends you all of your items that are still in other worlds.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Collect,
+    },
+    Command {
+        name: "remaining",
+        args: &[],
+        help: "Lists your items that have not been found yet.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Remaining,
+    },
+    Command {
+        name: "alias",
+        args: &[Arg::OptionalRest("name")],
+        help: "Changes the name other players see. Resets it if no name is given.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Alias,
+    },
+    Command {
+        name: "admin",
+        args: &[Arg::OptionalRest("command")],
+        help: "Runs an admin command. Use `!admin login <password>` to log in first.",
+        permission: PermissionLevel::Player,
+        kind: CommandKind::Admin,
+    },
+];
+
+pub(super) const ADMIN_COMMANDS: &[Command] = &[
+    Command {
+        name: "send",
+        args: &[Arg::Required("player"), Arg::Rest("item name")],
+        help: "Sends an item to a player. Put player names with spaces in double quotes.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminSend,
+    },
+    Command {
+        name: "release",
+        args: &[Arg::Rest("player")],
+        help: "Sends out all remaining items from the player's world.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminRelease,
+    },
+    Command {
+        name: "forfeit",
+        args: &[Arg::Rest("player")],
+        help: "Same as /release.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminRelease,
+    },
+    Command {
+        name: "collect",
+        args: &[Arg::Rest("player")],
+        help: "Sends the player all of their items that are still in other worlds.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminCollect,
+    },
+    Command {
+        name: "alias",
+        args: &[Arg::Required("player"), Arg::OptionalRest("name")],
+        help: "Changes the alias of a player. Resets it if no name is given. \
+            Put player names with spaces in double quotes.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminAlias,
+    },
+    Command {
+        name: "kick",
+        args: &[Arg::Rest("player")],
+        help: "Disconnects all clients of a player.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminKick,
+    },
+    Command {
+        name: "hint",
+        args: &[Arg::Required("player"), Arg::Rest("item name")],
+        help: "Reveals the location of an item for a player without costing hint points. \
+            Put player names with spaces in double quotes.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminHint,
+    },
+    Command {
+        name: "option",
+        args: &[Arg::Required("name"), Arg::Rest("value")],
+        help: "Changes a server option until the server restarts, e.g. `hint_cost` or `release_mode`.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminOption,
+    },
+    Command {
+        name: "save",
+        args: &[],
+        help: "Saves the server state.",
+        permission: PermissionLevel::Admin,
+        kind: CommandKind::AdminSave,
+    },
+];
+
+impl Command {
+    pub fn find(commands: &'static [Command], name: &str) -> Option<&'static Command> {
+        commands
+            .iter()
+            .find(|command| command.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the command with its arguments, e.g. `!countdown [seconds]`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("{}{}", self.permission.prefix(), self.name);
+
+        for arg in self.args {
+            match *arg {
+                Arg::Required(name) | Arg::Rest(name) => write!(usage, " <{name}>"),
+                Arg::Optional(name) | Arg::OptionalRest(name) => write!(usage, " [{name}]"),
+            }
+            .expect("BUG: writing to a string can't fail");
+        }
+
+        usage
+    }
+
+    /// Splits `input` into the arguments of this command.
+    /// Returns `None` if arguments are missing or superfluous.
+    pub fn parse_args(&self, input: &str) -> Option<Args> {
+        let mut rest = input.trim();
+        let mut values = Vec::with_capacity(self.args.len());
+
+        for arg in self.args {
+            let value = match arg {
+                Arg::Required(_) | Arg::Optional(_) => {
+                    let (word, remainder) = split_word(rest)?;
+                    rest = remainder.trim_start();
+                    word
+                }
+                Arg::Rest(_) | Arg::OptionalRest(_) => std::mem::take(&mut rest),
+            };
+
+            let value = match arg {
+                _ if !value.is_empty() => Some(value.to_owned()),
+                Arg::Optional(_) | Arg::OptionalRest(_) => None,
+                Arg::Required(_) | Arg::Rest(_) => return None,
+            };
+
+            values.push(value);
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(Args {
+            args: self.args,
+            values,
+        })
+    }
+}
+
+/// Splits off the first word of `input`, which may be put in double quotes to contain spaces.
+/// Returns `None` if the closing quote is missing or not followed by whitespace.
+fn split_word(input: &str) -> Option<(&str, &str)> {
+    let Some(quoted) = input.strip_prefix('"') else {
+        return Some(input.split_once(char::is_whitespace).unwrap_or((input, "")));
+    };
+    let (word, remainder) = quoted.split_once('"')?;
+
+    if !remainder.is_empty() && !remainder.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((word, remainder))
+}
+
+pub(super) struct Args {
+    args: &'static [Arg],
+    values: Vec<Option<String>>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let index = self.args.iter().position(|arg| arg.name() == name)?;
+
+        self.values[index].as_deref()
+    }
+
+    pub fn required(&self, name: &str) -> &str {
+        self.get(name)
+            .unwrap_or_else(|| panic!("BUG: missing required argument {name:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(commands: &'static [Command], name: &str, input: &str) -> Option<Vec<Option<String>>> {
+        let command = Command::find(commands, name).unwrap();
+
+        command.parse_args(input).map(|args| args.values)
+    }
+
+    fn values(values: &[Option<&str>]) -> Option<Vec<Option<String>>> {
+        Some(
+            values
+                .iter()
+                .map(|value| value.map(str::to_owned))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn quoted_player_names() {
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "send", r#""Slot Name" Progressive Sword"#),
+            values(&[Some("Slot Name"), Some("Progressive Sword")])
+        );
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "alias", r#""Slot Name""#),
+            values(&[Some("Slot Name"), None])
+        );
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "hint", r#"Player "Quoted Item""#),
+            values(&[Some("Player"), Some(r#""Quoted Item""#)])
+        );
+    }
+
+    #[test]
+    fn malformed_quotes() {
+        assert_eq!(parse(ADMIN_COMMANDS, "send", r#""Slot Name Sword"#), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "send", r#""Slot"Name Sword"#), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "send", r#""" Sword"#), None);
+    }
+
+    #[test]
+    fn missing_args() {
+        assert_eq!(parse(ADMIN_COMMANDS, "send", ""), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "send", "Player"), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "option", "name"), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "kick", "   "), None);
+        assert_eq!(parse(COMMANDS, "countdown", ""), values(&[None]));
+        assert_eq!(parse(COMMANDS, "alias", ""), values(&[None]));
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "alias", "Player"),
+            values(&[Some("Player"), None])
+        );
+    }
+
+    #[test]
+    fn rest_args_take_the_remaining_words() {
+        assert_eq!(
+            parse(COMMANDS, "hint", "Progressive Sword"),
+            values(&[Some("Progressive Sword")])
+        );
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "send", "  Player   Progressive  Sword  "),
+            values(&[Some("Player"), Some("Progressive  Sword")])
+        );
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "option", "hint_cost 10"),
+            values(&[Some("hint_cost"), Some("10")])
+        );
+        assert_eq!(
+            parse(ADMIN_COMMANDS, "release", "Some Player"),
+            values(&[Some("Some Player")])
+        );
+    }
+
+    #[test]
+    fn extra_args() {
+        assert_eq!(parse(COMMANDS, "help", "me"), None);
+        assert_eq!(parse(COMMANDS, "countdown", "5 6"), None);
+        assert_eq!(parse(ADMIN_COMMANDS, "save", "now"), None);
+        assert_eq!(parse(COMMANDS, "countdown", " 5 "), values(&[Some("5")]));
+    }
+}
@@ -1,8 +1,17 @@
 use std::path::{Path, PathBuf};
 
+use aprs_proto::primitives::SlotId;
+use aprs_server_core::DataStorageLimits;
+
+use crate::server::PermissionLevel;
+
 #[derive(Clone, Default)]
 pub struct Config {
     state_path: Option<PathBuf>,
+    console: bool,
+    data_storage_limits: DataStorageLimits,
+    data_storage_server_prefixes: Vec<String>,
+    data_storage_slot_prefixes: Vec<String>,
 }
 
 impl Config {
@@ -23,4 +32,177 @@ impl Config {
     pub fn state_path(&self) -> Option<&Path> {
         self.state_path.as_deref()
     }
+
+    /// Print chat messages to the terminal for the stdin console
+    pub fn with_console(mut self, console: bool) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn console(&self) -> bool {
+        self.console
+    }
+
+    pub fn with_data_storage_limits(mut self, limits: DataStorageLimits) -> Self {
+        self.data_storage_limits = limits;
+        self
+    }
+
+    pub fn data_storage_limits(&self) -> &DataStorageLimits {
+        &self.data_storage_limits
+    }
+
+    /// Data storage keys starting with any of `prefixes` may only be written by admins
+    pub fn with_data_storage_server_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.data_storage_server_prefixes = prefixes;
+        self
+    }
+
+    /// Data storage keys starting with any of `prefixes` followed by a slot id,
+    /// e.g. `slot_3` or `slot_3_inventory` for the prefix `slot_`,
+    /// may only be written by that slot.
+    /// Every team has its own data storage, so this is always a slot of the writer's team.
+    pub fn with_data_storage_slot_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.data_storage_slot_prefixes = prefixes;
+        self
+    }
+
+    /// Returns who may write `key` in the data storage of a team
+    pub fn data_storage_key_owner(&self, key: &str) -> KeyOwner {
+        if key.starts_with("_read_") {
+            return KeyOwner::Nobody;
+        }
+
+        let is_server_key = self
+            .data_storage_server_prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()));
+
+        if is_server_key {
+            return KeyOwner::Server;
+        }
+
+        for prefix in &self.data_storage_slot_prefixes {
+            let Some(rest) = key.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            let digits_len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+
+            // Keys that don't name a slot are reserved for the server
+            return match rest[..digits_len].parse() {
+                Ok(slot) => KeyOwner::Slot(SlotId(slot)),
+                Err(_) => KeyOwner::Server,
+            };
+        }
+
+        KeyOwner::Anyone
+    }
+}
+
+/// Who may write a data storage key
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum KeyOwner {
+    /// Read-only keys like `_read_hints_0_1`
+    Nobody,
+    /// Only admins and the server itself
+    Server,
+    /// Only the given slot of the team
+    Slot(SlotId),
+    Anyone,
+}
+
+impl KeyOwner {
+    pub fn may_be_written_by(self, slot: SlotId, permission_level: PermissionLevel) -> bool {
+        match self {
+            KeyOwner::Nobody => false,
+            KeyOwner::Server => permission_level >= PermissionLevel::Admin,
+            KeyOwner::Slot(owner) => owner == slot,
+            KeyOwner::Anyone => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::new()
+            .with_data_storage_server_prefixes(vec!["server_".into()])
+            .with_data_storage_slot_prefixes(vec!["slot_".into()])
+    }
+
+    fn owner(key: &str) -> KeyOwner {
+        config().data_storage_key_owner(key)
+    }
+
+    #[test]
+    fn read_only_keys() {
+        assert_eq!(owner("_read_hints_0_1"), KeyOwner::Nobody);
+        assert_eq!(owner("_read_"), KeyOwner::Nobody);
+    }
+
+    #[test]
+    fn server_keys() {
+        assert_eq!(owner("server_"), KeyOwner::Server);
+        assert_eq!(owner("server_motd"), KeyOwner::Server);
+        assert_eq!(owner("serve"), KeyOwner::Anyone);
+    }
+
+    #[test]
+    fn slot_keys() {
+        assert_eq!(owner("slot_3"), KeyOwner::Slot(SlotId(3)));
+        assert_eq!(owner("slot_3_inventory"), KeyOwner::Slot(SlotId(3)));
+        assert_eq!(owner("slot_03"), KeyOwner::Slot(SlotId(3)));
+        assert_eq!(owner("slot_12abc"), KeyOwner::Slot(SlotId(12)));
+    }
+
+    #[test]
+    fn slot_keys_without_slot_belong_to_the_server() {
+        assert_eq!(owner("slot_"), KeyOwner::Server);
+        assert_eq!(owner("slot_inventory"), KeyOwner::Server);
+        assert_eq!(owner("slot_-1"), KeyOwner::Server);
+        assert_eq!(owner("slot_99999999999999999999"), KeyOwner::Server);
+    }
+
+    #[test]
+    fn other_keys() {
+        assert_eq!(owner("inventory"), KeyOwner::Anyone);
+        assert_eq!(owner("my_slot_3"), KeyOwner::Anyone);
+        assert_eq!(owner(""), KeyOwner::Anyone);
+        assert_eq!(
+            Config::new().data_storage_key_owner("slot_3"),
+            KeyOwner::Anyone
+        );
+    }
+
+    #[test]
+    fn server_prefixes_take_precedence() {
+        let config = config().with_data_storage_server_prefixes(vec!["slot_0".into()]);
+
+        assert_eq!(
+            config.data_storage_key_owner("slot_0_shared"),
+            KeyOwner::Server
+        );
+        assert_eq!(
+            config.data_storage_key_owner("slot_1"),
+            KeyOwner::Slot(SlotId(1))
+        );
+    }
+
+    #[test]
+    fn writers() {
+        let player = PermissionLevel::Player;
+        let admin = PermissionLevel::Admin;
+
+        assert!(!KeyOwner::Nobody.may_be_written_by(SlotId(3), admin));
+        assert!(!KeyOwner::Server.may_be_written_by(SlotId(3), player));
+        assert!(KeyOwner::Server.may_be_written_by(SlotId(3), admin));
+        assert!(KeyOwner::Slot(SlotId(3)).may_be_written_by(SlotId(3), player));
+        assert!(!KeyOwner::Slot(SlotId(3)).may_be_written_by(SlotId(4), player));
+        assert!(!KeyOwner::Slot(SlotId(3)).may_be_written_by(SlotId(4), admin));
+        assert!(KeyOwner::Anyone.may_be_written_by(SlotId(3), player));
+    }
 }
@@ -0,0 +1,90 @@
+use aprs_proto::primitives::TeamId;
+use aprs_proto::server::PrintJson;
+use aprs_proto::server::print_json::JsonMessagePart;
+use tracing::info;
+
+use crate::server::ServerMessage;
+
+impl super::Server {
+    /// Handles a line typed into the stdin console.
+    /// Lines starting with `/` are admin commands, everything else is sent as chat.
+    pub(super) async fn on_console_command(&mut self, line: &str) {
+        let line = line.trim();
+
+        if line.is_empty() {
+            return;
+        }
+
+        if line.eq_ignore_ascii_case("/exit") {
+            // the state is saved once the remaining events have been handled
+            info!("Shutting down...");
+            self.client_message_receiver.close();
+            return;
+        }
+
+        if line.starts_with('/') {
+            let result = self.run_admin_command(line).await;
+            println!("{result}");
+            return;
+        }
+
+        self.broadcast(PrintJson::chat_message(format!("[Server]: {line}")))
+            .await;
+    }
+
+    /// Prints chat messages as text if the console is enabled
+    pub(super) fn print_to_console(&self, team: Option<TeamId>, message: &ServerMessage) {
+        if !self.config.console() {
+            return;
+        }
+
+        let ServerMessage::PrintJson(print_json) = message else {
+            return;
+        };
+
+        println!("{}", self.render_print_json(team, print_json));
+    }
+
+    /// Renders a `PrintJson` as plain text, resolving ids via the data package
+    fn render_print_json(&self, team: Option<TeamId>, print_json: &PrintJson) -> String {
+        let team = team
+            .or_else(|| {
+                let team = print_json.additional_info.as_ref()?.team?;
+                Some(TeamId(team.into()))
+            })
+            .unwrap_or(TeamId(0));
+
+        print_json
+            .data
+            .iter()
+            .map(|part| match part {
+                JsonMessagePart::Text { text }
+                | JsonMessagePart::EntranceName { text }
+                | JsonMessagePart::HintStatus { text, .. } => text.clone(),
+                JsonMessagePart::ItemId {
+                    item_id, player, ..
+                } => self
+                    .multi_data
+                    .item_name(*player, *item_id)
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("Unknown item (ID: {})", item_id.0)),
+                JsonMessagePart::LocationId {
+                    location_id,
+                    player,
+                } => self
+                    .multi_data
+                    .location_name(*player, *location_id)
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| format!("Unknown location (ID: {})", location_id.0)),
+                JsonMessagePart::PlayerId { player_id } => {
+                    self.player_alias(team, *player_id).to_owned()
+                }
+                JsonMessagePart::Other(value) => value
+                    .get("text")
+                    .and_then(|text| text.as_str())
+                    .unwrap_or_default()
+                    .to_owned(),
+            })
+            .collect()
+    }
+}
@@ -1,13 +1,17 @@
+use std::sync::Arc;
+
 use tokio::sync::oneshot;
 
 use crate::net::ClientAddr;
 use crate::server::client_id::ClientId;
 use crate::server::control::Control;
-use crate::server::{ClientMessages, ClientToServerConnection};
+use crate::server::{ClientMessages, ClientToServerConnection, ServerMessage};
 
 pub enum Event {
     ClientConnected(ClientAddr, oneshot::Sender<ClientToServerConnection>),
     ClientDisconnected(ClientId),
     ClientMessages(ClientId, ClientMessages),
     ClientControl(ClientId, Control),
+    Broadcast(Arc<ServerMessage>),
+    ConsoleCommand(String),
 }
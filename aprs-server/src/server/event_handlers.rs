@@ -4,21 +4,21 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use aprs_proto::client::{
-    Bounce, ClientStatus, Connect, Get, GetDataPackage, LocationChecks, LocationScouts, Say, Set,
-    SetNotify, StatusUpdate,
+    Bounce, ClientStatus, Connect, ConnectUpdate, CreateAsHint, CreateHints, Get, GetDataPackage,
+    LocationChecks, LocationScouts, Say, Set, SetNotify, StatusUpdate, UpdateHint,
 };
-use aprs_proto::primitives::LocationId;
+use aprs_proto::primitives::{LocationId, SlotId, TeamId};
+use aprs_proto::server::print_json::HintStatus;
 use aprs_proto::server::{
-    Bounced, CommandPermission, Connected, ConnectionRefused, DataPackage, DataPackageData,
-    GameData, LocationInfo, Message, NetworkItem, Permissions, PrintJson,
-    RemainingCommandPermission, Retrieved, RoomInfo, RoomUpdate, SetReply, Time,
+    Bounced, CommandPermission, Connected, ConnectionError, ConnectionRefused, DataPackage,
+    DataPackageData, GameData, InvalidPacket, LocationInfo, NetworkItem, PrintJson, Retrieved,
+    RoomInfo, RoomUpdate, SetReply, Time,
 };
-use aprs_server_core::bounce_matches;
+use aprs_server_core::{SetError, bounce_matches};
 use aprs_value::Str;
-use color_eyre::eyre::{ContextCompat, Result, bail};
+use color_eyre::eyre::{ContextCompat, Result};
 use fnv::{FnvHashMap, FnvHashSet};
 use itertools::Itertools;
-use levenshtein::levenshtein;
 use tokio::sync::{Mutex, oneshot};
 use tracing::{debug, error, info, warn};
 
@@ -47,6 +47,8 @@ impl super::Server {
             Event::ClientControl(client_id, control) => {
                 self.on_client_control(client_id, control).await
             }
+            Event::Broadcast(message) => self.broadcast(message).await,
+            Event::ConsoleCommand(command) => self.on_console_command(&command).await,
         }
     }
 
@@ -71,19 +73,13 @@ impl super::Server {
             .lock()
             .await
             .send(RoomInfo {
-                version: (0, 6, 6).into(),
+                version: Self::VERSION,
                 generator_version: self.multi_data.version,
                 tags: vec!["APRS".into(), "100% python and gluten free".into()],
                 password: self.multi_data.server_options.client_password.is_some(),
-                permissions: Permissions {
-                    release: CommandPermission::Auto,
-                    collect: CommandPermission::Auto,
-                    remaining: RemainingCommandPermission::Enabled,
-                },
-                // TODO: set hint cost properly
-                hint_cost: 10,
-                // TODO: set location check points properly
-                location_check_points: 20,
+                permissions: self.multi_data.server_options.permissions(),
+                hint_cost: self.multi_data.server_options.hint_cost,
+                location_check_points: self.multi_data.server_options.location_check_points,
                 games: self.multi_data.data_package.keys().cloned().collect(),
                 datapackage_checksums: self
                     .multi_data
@@ -107,7 +103,7 @@ impl super::Server {
 
         info!("Client disconnected: {client_id:?}, {address:?}");
 
-        self.clients.remove(&client_id);
+        self.remove_client(client_id).await;
     }
 
     async fn on_client_messages(&mut self, client_id: ClientId, messages: ClientMessages) {
@@ -116,6 +112,15 @@ impl super::Server {
         };
 
         for message in messages {
+            let message = match message {
+                Ok(message) => message,
+                Err(invalid_packet) => {
+                    debug!("Invalid client message: {invalid_packet:?}");
+                    client.lock().await.send(invalid_packet).await;
+                    continue;
+                }
+            };
+
             if let Err(err) = self.on_client_message(&client, message).await {
                 debug!("||| {err:?}");
                 client.lock().await.send_control(Close).await;
@@ -155,7 +160,14 @@ impl super::Server {
             match message {
                 ClientMessage::Connect(connect) => self.on_connect(client, connect).await?,
                 _ => {
-                    bail!("Client sent non-connect message before being connected: {message:?}")
+                    let cmd = message.cmd();
+                    let text = format!("Cannot do {cmd} before Connect");
+
+                    client
+                        .lock()
+                        .await
+                        .send(InvalidPacket::cmd(Some(cmd.into()), text))
+                        .await;
                 }
             }
 
@@ -164,7 +176,17 @@ impl super::Server {
 
         match message {
             ClientMessage::Connect(_) => {
-                bail!("Client already connected, but sent another connect message")
+                client
+                    .lock()
+                    .await
+                    .send(InvalidPacket::cmd(
+                        Some("Connect".into()),
+                        "Client is already connected",
+                    ))
+                    .await;
+            }
+            ClientMessage::ConnectUpdate(connect_update) => {
+                self.on_connect_update(client, connect_update).await
             }
             ClientMessage::Say(say) => self.on_say(client, say).await,
             ClientMessage::Get(get) => self.on_get(client, get).await,
@@ -184,82 +206,100 @@ impl super::Server {
                 error!("BUG: GetDataPackage should already be handled as unauthenticated packet");
             }
             ClientMessage::Bounce(bounce) => self.on_bounce(client, &bounce).await,
-            ClientMessage::Unknown(value) => {
-                warn!("Unknown client message: {value:?}");
+            ClientMessage::UpdateHint(update_hint) => {
+                self.on_update_hint(client, update_hint).await
+            }
+            ClientMessage::CreateHints(create_hints) => {
+                self.on_create_hints(client, create_hints).await
             }
         };
 
         Ok(())
     }
 
-    async fn on_connect(&self, client: &Mutex<Client>, connect: Connect) -> Result<()> {
+    async fn on_connect(&mut self, client: &Mutex<Client>, connect: Connect) -> Result<()> {
         let Connect {
             password,
             game,
             name: connect_name,
             uuid: _,
-            version: _,
+            version,
             items_handling,
             tags,
             slot_data,
         } = connect;
-        // TODO: implement checks:
-        // - items handling
-        // - version (skip if tags are appropriate)
+        let mut errors = Vec::new();
 
         // the password must match if one is required
         if let Some(client_password) = &self.multi_data.server_options.client_password
             && Some(client_password) != password.as_ref()
         {
-            client
-                .lock()
-                .await
-                .send(ConnectionRefused::invalid_password())
-                .await;
-            return Ok(());
+            errors.push(ConnectionError::InvalidPassword);
         }
 
-        // the requested slot name must exist
-        let Some(team_and_slot) = self.multi_data.connect_names.get(&connect_name) else {
-            client
-                .lock()
-                .await
-                .send(ConnectionRefused::invalid_slot())
-                .await;
-            return Ok(());
-        };
-        let TeamAndSlot { slot, team } = *team_and_slot;
-        let Some(slot_info) = self.multi_data.slot_info.get(&slot) else {
-            error!("Inconsistent multi data!");
+        // the requested slot name must exist and belong to a player
+        let team_and_slot = self.multi_data.connect_names.get(&connect_name);
+        let slot_info = team_and_slot.and_then(|team_and_slot| {
+            let slot_info = self.multi_data.slot_info.get(&team_and_slot.slot);
 
-            client
-                .lock()
-                .await
-                .send(ConnectionRefused::invalid_slot())
-                .await;
-            return Ok(());
-        };
+            if slot_info.is_none() {
+                error!("Inconsistent multi data!");
+            }
 
-        let skip_game_and_version_validation = tags
-            .iter()
-            .any(|tag| ["Tracker", "TextOnly", "HintGame"].contains(&tag.as_str()));
+            slot_info
+        });
 
-        // the requested slot game must match
-        if slot_info.game != game && !skip_game_and_version_validation {
+        match (team_and_slot, slot_info) {
+            (Some(team_and_slot), Some(slot_info)) if !slot_info.r#type.is_group() => {
+                let skip_game_and_version_validation = tags
+                    .iter()
+                    .any(|tag| ["Tracker", "TextOnly", "HintGame"].contains(&tag.as_str()));
+
+                // the requested slot game must match
+                if slot_info.game != game && !skip_game_and_version_validation {
+                    errors.push(ConnectionError::InvalidGame);
+                }
+
+                // the client must be at least as new as the slot requires
+                let minimum_version = self
+                    .multi_data
+                    .minimum_versions
+                    .clients
+                    .get(&team_and_slot.slot);
+
+                if let Some(minimum_version) = minimum_version
+                    && version < *minimum_version
+                    && !skip_game_and_version_validation
+                {
+                    errors.push(ConnectionError::IncompatibleVersion);
+                }
+            }
+            _ => errors.push(ConnectionError::InvalidSlot),
+        }
+
+        // the requested items handling must be consistent
+        if !items_handling.is_valid() {
+            errors.push(ConnectionError::InvalidItemsHandling);
+        }
+
+        let (Some(team_and_slot), Some(slot_info), true) =
+            (team_and_slot, slot_info, errors.is_empty())
+        else {
             client
                 .lock()
                 .await
-                .send(ConnectionRefused::invalid_game())
+                .send(ConnectionRefused::from(errors))
                 .await;
             return Ok(());
-        }
+        };
+        let TeamAndSlot { slot, team } = *team_and_slot;
 
         {
             let mut client = client.lock().await;
 
             let slot_state = self
                 .state
-                .get_slot_state(slot)
+                .get_slot_state(team, slot)
                 .context("BUG: missing slot state for slot {slot}")?;
 
             if items_handling.is_starting_inventory() {
@@ -289,8 +329,7 @@ impl super::Server {
                         .filter(|_| slot_data)
                         .cloned(),
                     slot_info: self.multi_data.slot_info.clone(),
-                    // TODO: sent actual hintpoints
-                    hint_points: 0,
+                    hint_points: self.hint_points(team, slot),
                 })
                 .await;
 
@@ -302,113 +341,82 @@ impl super::Server {
             client.team_id = team;
             client.tags = FnvHashSet::from_iter(tags);
             client.game = game;
+            client.version = version;
             client.is_connected = true;
         }
 
         self.sync_items_to_client(client).await;
 
-        Ok(())
-    }
-
-    async fn on_say(&mut self, client: &Mutex<Client>, say: Say) {
-        let Say { text } = say;
-        let text = text.trim();
-
-        let slot = client.lock().await.slot_id;
-        let message = PrintJson::builder()
-            .with_player(slot)
-            .with_text(": ")
-            .with_text(text)
-            .build();
-
-        self.broadcast(message).await;
+        let join = {
+            let client = client.lock().await;
+            let tags = client.sorted_tags();
+
+            PrintJson::join(
+                client.team_id,
+                client.slot_id,
+                &client.game,
+                client.version,
+                tags,
+            )
+        };
 
-        if let Some(item) = text.strip_prefix("!hint ") {
-            self.on_command_hint(client, item).await;
-        } else if text == "!release" {
-            self.on_goal_complete(client).await;
-        }
-    }
+        self.broadcast(join).await;
 
-    async fn on_command_hint(&self, client: &Mutex<Client>, needle_item: &str) {
-        let needle_item = needle_item.trim();
-        let slot = client.lock().await.slot_id;
+        // the status is only reset to unknown once the slot's last client is gone
+        let status_is_unknown = self
+            .state
+            .get_slot_state(team, slot)
+            .is_some_and(|slot_state| slot_state.client_status() == ClientStatus::Unknown);
 
-        if needle_item.is_empty() {
-            self.broadcast(PrintJson::chat_message("Usage: !hint <item name>"))
+        if status_is_unknown {
+            self.update_client_status(team, slot, ClientStatus::Connected)
                 .await;
-            return;
         }
 
-        let Some(slot_info) = self.multi_data.get_slot_info(slot) else {
-            error!("BUG: tried to get slot_info for invalid slot {slot:?}");
-            return;
-        };
-        let game = &slot_info.game;
-        let Some(game_data) = self.multi_data.get_game_data(game) else {
-            error!("BUG: tried to get game data for invalid game {game:?}");
-            return;
-        };
+        Ok(())
+    }
 
-        let Some((found_item_name, found_item_id, confidence)) = game_data
-            .item_name_to_id
-            .iter()
-            .map(|(item_name, item_id)| {
-                let distance = levenshtein(needle_item, item_name);
-                let confidence =
-                    (distance.min(needle_item.len()) as f32) / (needle_item.len() as f32);
-                let confidence = 1.0 - confidence;
-                let confidence = (confidence * 100.).floor() as u32;
-
-                (item_name, item_id, confidence)
-            })
-            .max_by_key(|(_, _, confidence)| *confidence)
-        else {
-            // TODO: maybe broadcast message in this case
-            error!("BUG: world doesn't seem to have any items?");
-            return;
-        };
+    async fn on_say(&mut self, client: &Mutex<Client>, say: Say) {
+        let Say { text } = say;
+        let text = text.trim();
 
-        let confidence_threshold = 70;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let command = text.strip_prefix('!');
+        // admin commands may contain the admin password, so they are never echoed
+        let is_admin_command = command.is_some_and(|command| {
+            let name = command.split_whitespace().next().unwrap_or_default();
+            name.eq_ignore_ascii_case("admin")
+        });
+
+        if !is_admin_command {
+            let message = PrintJson::builder()
+                .with_player(slot)
+                .with_text(": ")
+                .with_text(text)
+                .build();
 
-        if confidence < confidence_threshold {
-            self.broadcast(PrintJson::chat_message(format!(
-                "No matching item found. Did you mean '{found_item_name}'? ({confidence}% match)"
-            )))
-            .await;
-            return;
+            self.broadcast_team(team, message).await;
         }
 
-        // TOOD: get first uncollected item by sphere
-        let Some((item_slot, item_location, flags)) =
-            self.multi_data.find_item_location(*found_item_id)
-        else {
-            // TODO: maybe broadcast message in this case
-            error!("BUG: item does not seem to be placed?");
-            return;
-        };
-
-        self.broadcast(
-            PrintJson::builder()
-                .with_player(slot)
-                .with_text("'s ")
-                .with_item(slot, *found_item_id, flags)
-                .with_text(" is at ")
-                .with_player(item_slot)
-                .with_text("'s ")
-                .with_location(item_slot, item_location)
-                .build(),
-        )
-        .await;
+        if let Some(command) = command {
+            self.on_command(client, command).await;
+        }
     }
 
     async fn on_get(&mut self, client: &Mutex<Client>, get: Get) {
         let Get { keys } = get;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
 
         let mut retrieved = FnvHashMap::default();
 
         for key in keys {
-            if let Some(value) = self.get_key(&key) {
+            if let Some(value) = self.get_key(team, slot, &key) {
                 retrieved.insert(key, value);
             }
         }
@@ -428,27 +436,44 @@ impl super::Server {
             operations: _,
         } = set;
 
-        if key.starts_with("_read_") {
-            warn!("invalid datastorage Set key: {key}");
+        let (slot, team, permission_level) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id, client.permission_level)
+        };
+        let may_write = self
+            .config
+            .data_storage_key_owner(key)
+            .may_be_written_by(slot, permission_level);
+
+        if !may_write {
+            let message =
+                InvalidPacket::arguments("Set", format!("Set: No Permission for key `{key}`"));
+
+            client.lock().await.send(message).await;
             return;
         }
 
-        let slot = client.lock().await.slot_id;
-
-        let (original_value, value) = match self.state.data_storage.set(&set) {
+        let limits = self.config.data_storage_limits();
+        let (original_value, value) = match self.state.set_data_storage(team, &set, limits) {
             Ok(value) => value,
-            Err(err) => {
+            Err(SetError::Operation(err)) => {
                 warn!("DataStorage set failed: {err}");
                 return;
             }
+            Err(err) => {
+                let message = InvalidPacket::arguments("Set", format!("Set: {err}"));
+
+                client.lock().await.send(message).await;
+                return;
+            }
         };
 
-        let set_reply = Arc::new(Message::SetReply(SetReply {
+        let set_reply = SetReply {
             key: key.clone(),
             value,
             original_value,
             slot,
-        }));
+        };
 
         {
             let client = client.lock().await;
@@ -458,13 +483,7 @@ impl super::Server {
             }
         }
 
-        for client in self.clients.values() {
-            let client = client.lock().await;
-
-            if client.wants_updates_for_keys.contains(key.as_str()) {
-                client.send(set_reply.clone()).await;
-            }
-        }
+        self.notify_key_watchers(team, set_reply).await;
     }
 
     async fn on_set_notify(&mut self, client: &Mutex<Client>, set_notify: SetNotify) {
@@ -483,11 +502,12 @@ impl super::Server {
     ) {
         let LocationScouts {
             locations,
-            create_as_hint: _,
+            create_as_hint,
         } = location_scouts;
-        let slot = client.lock().await.slot_id;
-
-        // TODO: handle create_as_hint
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
 
         let locations = locations.into_iter()
             .filter_map(|location_id| {
@@ -509,7 +529,153 @@ impl super::Server {
             })
             .collect::<Vec<_>>();
 
+        let hints = locations
+            .iter()
+            .filter(|_| create_as_hint.creates_hints())
+            .filter_map(|item| self.create_hint(team, slot, item.location, HintStatus::Unspecified))
+            .collect_vec();
+
         client.lock().await.send(LocationInfo { locations }).await;
+
+        if hints.is_empty() {
+            return;
+        }
+
+        self.update_hints(team, slot, hints.clone()).await;
+
+        if create_as_hint == CreateAsHint::Broadcast {
+            self.send_hint_messages(team, &hints).await;
+        }
+    }
+
+    async fn on_update_hint(&mut self, client: &Mutex<Client>, update_hint: UpdateHint) {
+        let UpdateHint {
+            player,
+            location,
+            status,
+        } = update_hint;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+
+        // unknown hints are ignored
+        let Some(hint) = self.state.find_hint(team, player, location) else {
+            return;
+        };
+
+        // only the receiving player may change the hint status
+        if hint.receiving_player != slot {
+            client
+                .lock()
+                .await
+                .send(InvalidPacket::arguments(
+                    "UpdateHint",
+                    "UpdateHint: No Permission",
+                ))
+                .await;
+            return;
+        }
+
+        let Some(status) = status else {
+            return;
+        };
+
+        if status == HintStatus::Found {
+            client
+                .lock()
+                .await
+                .send(InvalidPacket::arguments(
+                    "UpdateHint",
+                    "UpdateHint: Cannot manually update status to found",
+                ))
+                .await;
+            return;
+        }
+
+        // found hints stay found
+        if hint.found || hint.status == status {
+            return;
+        }
+
+        let mut hint = hint.clone();
+        hint.status = status;
+
+        self.update_hints(team, slot, vec![hint]).await;
+    }
+
+    async fn on_create_hints(&mut self, client: &Mutex<Client>, create_hints: CreateHints) {
+        let CreateHints {
+            locations,
+            player,
+            status,
+        } = create_hints;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let finding_slot = player.unwrap_or(slot);
+        let mut hints = Vec::new();
+
+        let error = 'validate: {
+            if locations.is_empty() {
+                break 'validate Some("CreateHints: No locations specified.");
+            }
+
+            // hinting other worlds may leak spoilers
+            if finding_slot != slot && self.multi_data.is_race() {
+                break 'validate Some("CreateHints: Cannot hint other worlds in a race.");
+            }
+
+            for location in locations {
+                let Some(location_info) = self.multi_data.location_info(finding_slot, location)
+                else {
+                    if finding_slot != slot {
+                        break 'validate Some(
+                            "CreateHints: One or more of the locations do not exist \
+                            for the specified off-world player.",
+                        );
+                    }
+
+                    continue;
+                };
+
+                // hints for other players' items are limited to own locations
+                if location_info.slot != slot {
+                    if status != HintStatus::Unspecified {
+                        break 'validate Some(
+                            "CreateHints: Must use HINT_UNSPECIFIED for location hint.",
+                        );
+                    }
+
+                    if finding_slot != slot {
+                        break 'validate Some(
+                            "CreateHints: Can only create hints for own items or locations.",
+                        );
+                    }
+                }
+
+                hints.extend(self.create_hint(team, finding_slot, location, status));
+            }
+
+            None
+        };
+
+        if let Some(error) = error {
+            client
+                .lock()
+                .await
+                .send(InvalidPacket::arguments("CreateHints", error))
+                .await;
+            return;
+        }
+
+        if hints.is_empty() {
+            return;
+        }
+
+        self.update_hints(team, slot, hints.clone()).await;
+        self.send_hint_messages(team, &hints).await;
     }
 
     async fn on_location_checks(
@@ -518,30 +684,43 @@ impl super::Server {
         location_checks: LocationChecks,
     ) {
         let LocationChecks { locations } = location_checks;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
 
-        self.check_locations(client, locations).await;
+        self.check_locations(team, slot, locations).await;
     }
 
-    async fn check_locations(&mut self, client: &Mutex<Client>, locations: FnvHashSet<LocationId>) {
-        let slot_sending = client.lock().await.slot_id;
-
+    async fn check_locations(
+        &mut self,
+        team: TeamId,
+        slot_sending: SlotId,
+        locations: FnvHashSet<LocationId>,
+    ) {
         let Some(location_infos) = self.multi_data.get_locations(slot_sending) else {
             error!("BUG: missing location info for slot {slot_sending:?}");
             return;
         };
 
-        let Some(state) = self.state.get_slot_state_mut(slot_sending) else {
+        if self.state.get_slot_state(team, slot_sending).is_none() {
             error!("BUG: missing state for slot {slot_sending:?}");
             return;
-        };
+        }
 
-        let items_by_slot = locations
+        let newly_checked_locations = locations
             .iter()
-            .filter_map(|location| {
-                if state.check_location(*location).location_was_checked() {
-                    return None;
-                }
+            .copied()
+            .filter(|location| {
+                self.state
+                    .check_location(team, slot_sending, *location)
+                    .location_was_unchecked()
+            })
+            .collect_vec();
 
+        let items_by_slot = newly_checked_locations
+            .iter()
+            .filter_map(|location| {
                 let location_info = location_infos.get(location)?;
                 let network_item = NetworkItem {
                     item: location_info.item,
@@ -555,17 +734,17 @@ impl super::Server {
             .into_group_map();
 
         // we have nothing to do
-        if items_by_slot.is_empty() {
+        if newly_checked_locations.is_empty() {
             return;
         }
 
         let mut chat_messages = Vec::new();
 
         for (slot_receiving, items) in items_by_slot {
-            let Some(slot_state) = self.state.get_slot_state_mut(slot_receiving) else {
+            if self.state.get_slot_state(team, slot_receiving).is_none() {
                 error!("Tried to add items to invalid slot {slot_receiving:?}");
                 continue;
-            };
+            }
 
             for item in &items {
                 let message = PrintJson::chat_message_for_received_item(*item, slot_receiving);
@@ -573,42 +752,116 @@ impl super::Server {
                 chat_messages.push(message.into());
             }
 
-            slot_state.add_received_items(items);
+            self.state.add_received_items(team, slot_receiving, items);
         }
 
-        self.save_state();
-        self.broadcast_slot(slot_sending, RoomUpdate::checked_locations(locations))
-            .await;
+        let found_hints = newly_checked_locations
+            .iter()
+            .filter_map(|location| self.state.find_hint(team, slot_sending, *location))
+            .filter(|hint| !hint.found)
+            .cloned()
+            .map(|mut hint| {
+                hint.mark_found();
+                hint
+            })
+            .collect_vec();
+
+        self.update_hints(team, slot_sending, found_hints).await;
+        let room_update = RoomUpdate::checked_locations(locations)
+            .with_hint_points(self.hint_points(team, slot_sending));
+        self.broadcast_slot(team, slot_sending, room_update).await;
         self.sync_items_to_clients().await;
-        self.broadcast_messages(&chat_messages).await;
+        self.broadcast_team_messages(team, &chat_messages).await;
         // TODO: send RoomUpdate for checked_locations
     }
 
     async fn on_status_update(&mut self, client: &Mutex<Client>, status_update: StatusUpdate) {
         let StatusUpdate { status } = status_update;
+        let (slot, team) = {
+            let client = client.lock().await;
+            (client.slot_id, client.team_id)
+        };
+        let status_changed = self.update_client_status(team, slot, status).await;
 
-        // TODO: handle other status updates
-        match status {
-            ClientStatus::Unknown => {}
-            ClientStatus::Connected => {}
-            ClientStatus::Ready => {}
-            ClientStatus::Playing => {}
-            ClientStatus::Goal => {
-                self.on_goal_complete(client).await;
-            }
+        if status_changed && status == ClientStatus::Goal {
+            self.on_goal_complete(team, slot).await;
+        }
+    }
+
+    /// Sets the status of the slot and notifies clients watching its status key.
+    /// Returns `false` if the status stayed the same.
+    async fn update_client_status(
+        &mut self,
+        team: TeamId,
+        slot: SlotId,
+        status: ClientStatus,
+    ) -> bool {
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
+            error!("Tried to get slot state for unknown slot {slot:?}");
+            return false;
         };
+
+        // a completed goal is final
+        if slot_state.goal_completed() || slot_state.client_status() == status {
+            return false;
+        }
+
+        let original_value = self.client_status_value(team, slot);
+        self.state.set_client_status(team, slot, status);
+
+        self.notify_key_watchers(
+            team,
+            SetReply {
+                key: Self::client_status_key(team, slot),
+                value: self.client_status_value(team, slot),
+                original_value,
+                slot,
+            },
+        )
+        .await;
+
+        true
     }
 
-    async fn on_goal_complete(&mut self, client: &Mutex<Client>) {
-        let slot = client.lock().await.slot_id;
-        let Some(slot_state) = self.state.get_slot_state(slot) else {
+    async fn on_goal_complete(&mut self, team: TeamId, slot: SlotId) {
+        self.broadcast(PrintJson::goal(team, slot)).await;
+
+        let release_permission =
+            CommandPermission::from(self.multi_data.server_options.release_mode);
+
+        if release_permission.is_auto() {
+            self.release(team, slot).await;
+        }
+
+        let collect_permission =
+            CommandPermission::from(self.multi_data.server_options.collect_mode);
+
+        if collect_permission.is_auto() {
+            self.collect(team, slot).await;
+        }
+    }
+
+    /// Sends out all items that are still missing from the slot's world
+    pub(super) async fn release(&mut self, team: TeamId, slot: SlotId) {
+        let Some(slot_state) = self.state.get_slot_state(team, slot) else {
             error!("Tried to get slot state for unknown slot {slot:?}");
             return;
         };
         let missing_locations = slot_state.missing_locations().clone();
 
-        // TODO: handle disabled autocollect
-        self.check_locations(client, missing_locations).await;
+        self.broadcast(PrintJson::release(team, slot)).await;
+        self.check_locations(team, slot, missing_locations).await;
+    }
+
+    /// Sends the slot all of its items that are still missing from other worlds
+    pub(super) async fn collect(&mut self, team: TeamId, slot: SlotId) {
+        let uncollected_locations = self.uncollected_locations(team, slot);
+
+        self.broadcast(PrintJson::collect(team, slot)).await;
+
+        for (finding_slot, locations) in uncollected_locations {
+            self.check_locations(team, finding_slot, locations).await;
+        }
     }
 
     async fn on_get_data_package(
@@ -648,6 +901,66 @@ impl super::Server {
             .await;
     }
 
+    async fn on_connect_update(&mut self, client: &Mutex<Client>, connect_update: ConnectUpdate) {
+        let ConnectUpdate {
+            items_handling,
+            tags,
+        } = connect_update;
+
+        if let Some(items_handling) = items_handling {
+            if !items_handling.is_valid() {
+                client
+                    .lock()
+                    .await
+                    .send(InvalidPacket::arguments(
+                        "ConnectUpdate",
+                        "items_handling is invalid",
+                    ))
+                    .await;
+                return;
+            }
+
+            {
+                let mut client = client.lock().await;
+
+                if items_handling.is_starting_inventory() {
+                    let starting_inventory = self
+                        .multi_data
+                        .precollected_items
+                        .get(&client.slot_id)
+                        .map(Cow::Borrowed)
+                        .unwrap_or_default();
+
+                    client.set_starting_inventory(&starting_inventory);
+                }
+
+                client.set_items_handling(items_handling);
+            }
+
+            self.sync_items_to_client(client).await;
+        }
+
+        if let Some(tags) = tags {
+            let new_tags = FnvHashSet::from_iter(tags);
+            let (team, slot, old_tags, new_tags) = {
+                let mut client = client.lock().await;
+
+                if client.tags == new_tags {
+                    return;
+                }
+
+                let old_tags = client.sorted_tags();
+                client.tags = new_tags;
+                let new_tags = client.sorted_tags();
+
+                (client.team_id, client.slot_id, old_tags, new_tags)
+            };
+
+            self.broadcast(PrintJson::tags_changed(team, slot, &old_tags, new_tags))
+                .await;
+        }
+    }
+
     async fn on_sync(&mut self, client: &Mutex<Client>) {
         client.lock().await.reset_received_items();
         self.sync_items_to_client(client).await;
@@ -669,6 +982,197 @@ impl super::Server {
     }
 
     async fn on_close(&mut self, client: &Mutex<Client>) {
-        self.clients.remove(&client.lock().await.id);
+        let client_id = client.lock().await.id;
+
+        self.remove_client(client_id).await;
+    }
+
+    /// Removes the client and notifies the other players if it was connected to a slot
+    async fn remove_client(&mut self, client_id: ClientId) {
+        let Some(client) = self.clients.remove(&client_id) else {
+            return;
+        };
+
+        let (team, slot, part) = {
+            let client = client.lock().await;
+
+            if !client.is_connected {
+                return;
+            }
+
+            let tags = client.sorted_tags();
+            let part = PrintJson::part(client.team_id, client.slot_id, client.version, tags);
+
+            (client.team_id, client.slot_id, part)
+        };
+
+        self.broadcast(part).await;
+
+        for client in self.clients.values() {
+            let client = client.lock().await;
+
+            if client.is_connected && client.team_id == team && client.slot_id == slot {
+                return;
+            }
+        }
+
+        // the slot's last client is gone
+        self.update_client_status(team, slot, ClientStatus::Unknown)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aprs_proto::client::ItemsHandling;
+    use aprs_proto::common::NetworkVersion;
+    use aprs_proto::primitives::ConnectName;
+    use futures::FutureExt;
+    use serde_json::json;
+    use smallvec::smallvec;
+
+    use super::*;
+    use crate::game::MultiData;
+    use crate::server::control::ControlOrMessage;
+    use crate::server::{Config, Server};
+    use crate::websocket::decode_messages;
+
+    const TEAM: TeamId = TeamId(0);
+    const SLOT: SlotId = SlotId(1);
+
+    fn server() -> Server {
+        Server::new(Config::new(), MultiData::for_tests(&["Player1"], "Clique")).unwrap()
+    }
+
+    /// Connects a new client to `Player1` and returns its id and its end of the connection
+    async fn connect_client(server: &mut Server) -> (ClientId, ClientToServerConnection) {
+        let (reply_tx, mut reply_rx) = oneshot::channel();
+        let known_ids = server.clients.keys().copied().collect_vec();
+
+        server.on_client_connected(ClientAddr::Unix, reply_tx).await;
+
+        let client_id = *server
+            .clients
+            .keys()
+            .find(|client_id| !known_ids.contains(client_id))
+            .unwrap();
+        let connect = Connect {
+            password: None,
+            game: "Clique".into(),
+            name: ConnectName("Player1".into()),
+            uuid: String::new(),
+            version: NetworkVersion::new(0, 6, 1),
+            items_handling: ItemsHandling::empty(),
+            tags: vec!["AP".into()],
+            slot_data: false,
+        };
+
+        server
+            .on_client_messages(client_id, smallvec![Ok(ClientMessage::Connect(connect))])
+            .await;
+        assert!(server.clients[&client_id].lock().await.is_connected);
+
+        (client_id, reply_rx.try_recv().unwrap())
+    }
+
+    /// Returns the messages the server has sent to the client so far
+    fn received_messages(connection: &mut ClientToServerConnection) -> Vec<Arc<ServerMessage>> {
+        let mut messages = Vec::new();
+
+        while let Some(Some(control_or_message)) = connection.recv().now_or_never() {
+            if let ControlOrMessage::Message(message) = control_or_message {
+                messages.push(message);
+            }
+        }
+
+        messages
+    }
+
+    fn status(server: &Server) -> ClientStatus {
+        server
+            .state
+            .get_slot_state(TEAM, SLOT)
+            .unwrap()
+            .client_status()
+    }
+
+    #[tokio::test]
+    async fn connected_until_the_last_client_is_gone() {
+        let mut server = server();
+
+        assert_eq!(status(&server), ClientStatus::Unknown);
+
+        let (first, _first) = connect_client(&mut server).await;
+        assert_eq!(status(&server), ClientStatus::Connected);
+
+        let (second, _second) = connect_client(&mut server).await;
+        server.remove_client(first).await;
+        assert_eq!(status(&server), ClientStatus::Connected);
+
+        server.remove_client(second).await;
+        assert_eq!(status(&server), ClientStatus::Unknown);
+
+        connect_client(&mut server).await;
+        assert_eq!(status(&server), ClientStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn connecting_keeps_a_known_status() {
+        let mut server = server();
+        let (first, _first) = connect_client(&mut server).await;
+
+        server
+            .update_client_status(TEAM, SLOT, ClientStatus::Playing)
+            .await;
+
+        let (second, _second) = connect_client(&mut server).await;
+        assert_eq!(status(&server), ClientStatus::Playing);
+
+        // a completed goal even survives disconnecting
+        server
+            .update_client_status(TEAM, SLOT, ClientStatus::Goal)
+            .await;
+        server.remove_client(first).await;
+        server.remove_client(second).await;
+        connect_client(&mut server).await;
+        assert_eq!(status(&server), ClientStatus::Goal);
+    }
+
+    #[tokio::test]
+    async fn mixed_batches_dispatch_the_valid_packets() {
+        let mut server = server();
+        let (client_id, mut connection) = connect_client(&mut server).await;
+
+        received_messages(&mut connection);
+
+        let messages = decode_messages(Ok(vec![
+            json!({"cmd": "StatusUpdate", "status": 20}),
+            json!({"cmd": "Dance"}),
+            json!({"cmd": "Say", "text": 1}),
+            json!({"cmd": "Get", "keys": ["key"]}),
+            json!({"text": "hi"}),
+        ]));
+
+        server.on_client_messages(client_id, messages).await;
+
+        let messages = received_messages(&mut connection);
+        let invalid_packets = messages
+            .iter()
+            .filter_map(|message| match &**message {
+                ServerMessage::InvalidPacket(invalid_packet) => {
+                    Some(invalid_packet.original_cmd.as_deref())
+                }
+                _ => None,
+            })
+            .collect_vec();
+
+        assert_eq!(status(&server), ClientStatus::Playing);
+        assert!(
+            messages
+                .iter()
+                .any(|message| matches!(**message, ServerMessage::Retrieved(_)))
+        );
+        assert_eq!(invalid_packets, [Some("Dance"), Some("Say"), None]);
+        assert!(server.clients[&client_id].lock().await.is_connected);
     }
 }
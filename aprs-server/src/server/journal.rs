@@ -0,0 +1,467 @@
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context, Result, bail};
+use fnv::FnvHasher;
+use tracing::warn;
+
+use crate::server::state::{Mutation, State};
+
+/// Size of the length and checksum in front of every record
+const HEADER_SIZE: usize = 4 + 8;
+
+/// An append-only log of the mutations made since the last snapshot of the [`State`].
+///
+/// Each record is stored as `[length: u32][checksum: u64][payload]` (little endian),
+/// where the payload is the MessagePack encoded `(sequence, mutation)`
+/// and the checksum is the FNV-1a hash of the payload.
+pub struct Journal {
+    file: File,
+    size: u64,
+    buffer: Vec<u8>,
+    /// Makes [`Journal::append`] fail
+    #[cfg(test)]
+    pub fail_appends: bool,
+}
+
+impl Journal {
+    /// Returns the path of the journal belonging to the state at `state_path`
+    pub fn path_for(state_path: &Path) -> PathBuf {
+        let mut path = state_path.as_os_str().to_owned();
+        path.push(".journal");
+        path.into()
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open journal {path:?}"))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            file,
+            size,
+            buffer: Vec::new(),
+            #[cfg(test)]
+            fail_appends: false,
+        })
+    }
+
+    /// Applies all records that are newer than the `state` and returns how many were applied.
+    /// An incomplete or corrupt record at the end, e.g. from a crash during a write, is cut off.
+    pub fn replay(path: &Path, state: &mut State) -> Result<usize> {
+        let mut file = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        let mut offset = 0;
+        let mut num_applied = 0;
+
+        while let Some((sequence, mutation, record_size)) = decode_record(&data[offset..]) {
+            offset += record_size;
+
+            if sequence <= state.sequence() {
+                continue;
+            }
+
+            if sequence != state.sequence() + 1 {
+                bail!(
+                    "journal {path:?} continues at mutation {sequence}, \
+                    but the state ends at mutation {}",
+                    state.sequence()
+                );
+            }
+
+            state.apply(&mutation);
+            num_applied += 1;
+        }
+
+        if offset < data.len() {
+            warn!(
+                "Cutting off {} byte(s) of incomplete journal records from {path:?}",
+                data.len() - offset
+            );
+            file.set_len(offset as u64)?;
+            file.sync_all()?;
+        }
+
+        Ok(num_applied)
+    }
+
+    /// Buffers the mutation as the record following `state` and then applies it to `state`.
+    /// The mutation is applied even if it can't be buffered, so `state` keeps up with the server.
+    pub fn record(&mut self, state: &mut State, mutation: &Mutation) -> Result<()> {
+        let result = self.append(state.sequence() + 1, mutation);
+        state.apply(mutation);
+        result
+    }
+
+    /// Buffers a record. It is only written by [`Journal::sync`].
+    pub fn append(&mut self, sequence: u64, mutation: &Mutation) -> Result<()> {
+        #[cfg(test)]
+        if self.fail_appends {
+            bail!("appending to the journal failed");
+        }
+
+        let payload = rmp_serde::to_vec_named(&(sequence, mutation))?;
+        let length = u32::try_from(payload.len()).context("journal record too large")?;
+
+        self.buffer.extend(length.to_le_bytes());
+        self.buffer.extend(checksum(&payload).to_le_bytes());
+        self.buffer.extend(payload);
+
+        Ok(())
+    }
+
+    /// Writes all buffered records and waits until they are on disk
+    pub fn sync(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.file.write_all(&self.buffer)?;
+        self.file.sync_data()?;
+        self.size += self.buffer.len() as u64;
+        self.buffer.clear();
+
+        Ok(())
+    }
+
+    /// Removes all records, e.g. after they have been compacted into a snapshot
+    pub fn truncate(&mut self) -> Result<()> {
+        self.buffer.clear();
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    /// The size of the written records in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Returns `(sequence, mutation, record_size)` of the first record in `data`
+fn decode_record(data: &[u8]) -> Option<(u64, Mutation, usize)> {
+    let (length, rest) = data.split_first_chunk::<4>()?;
+    let (expected_checksum, rest) = rest.split_first_chunk::<8>()?;
+    let length = u32::from_le_bytes(*length) as usize;
+    let payload = rest.get(..length)?;
+
+    if checksum(payload) != u64::from_le_bytes(*expected_checksum) {
+        return None;
+    }
+
+    let (sequence, mutation) = rmp_serde::from_slice(payload).ok()?;
+
+    Some((sequence, mutation, HEADER_SIZE + length))
+}
+
+fn checksum(payload: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(payload);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use aprs_proto::client::{ClientStatus, Set, SetOperation};
+    use aprs_proto::primitives::{ItemId, LocationId, SlotId, TeamId};
+    use aprs_proto::server::print_json::HintStatus;
+    use aprs_proto::server::{Hint, NetworkItem};
+    use aprs_server_core::DataStorageLimits;
+    use aprs_value::Value;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const TEAM: TeamId = TeamId(0);
+    const SLOTS: [SlotId; 2] = [SlotId(1), SlotId(2)];
+    const LOCATIONS: [LocationId; 3] = [LocationId(10), LocationId(11), LocationId(12)];
+
+    struct Setup {
+        _dir: TempDir,
+        state_path: PathBuf,
+        journal_path: PathBuf,
+        /// The state of the server
+        live: State,
+        /// The copy kept by the persistence thread
+        replica: State,
+        journal: Journal,
+    }
+
+    impl Setup {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let state_path = dir.path().join("test.aprs.state");
+            let journal_path = Journal::path_for(&state_path);
+            let live = State::for_tests(&SLOTS, &LOCATIONS);
+            let replica = live.clone();
+            let journal = Journal::open(&journal_path).unwrap();
+
+            Self {
+                _dir: dir,
+                state_path,
+                journal_path,
+                live,
+                replica,
+                journal,
+            }
+        }
+
+        /// Journals the pending mutations of the live state like the persistence thread
+        fn persist(&mut self) {
+            for mutation in self.live.take_mutations() {
+                self.journal.record(&mut self.replica, &mutation).unwrap();
+            }
+
+            self.journal.sync().unwrap();
+        }
+
+        /// Saves the replica without truncating the journal
+        fn save_snapshot(&self) {
+            self.replica.save(&self.state_path).unwrap();
+        }
+
+        /// Loads the state like the server does on startup
+        fn load(&self) -> Result<(State, usize)> {
+            let mut state = State::try_load(&self.state_path)
+                .unwrap()
+                .unwrap_or_else(|| State::for_tests(&SLOTS, &LOCATIONS));
+            let num_replayed = Journal::replay(&self.journal_path, &mut state)?;
+
+            Ok((state, num_replayed))
+        }
+
+        fn append_raw(&self, bytes: &[u8]) {
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(&self.journal_path)
+                .unwrap();
+
+            file.write_all(bytes).unwrap();
+        }
+
+        fn journal_len(&self) -> u64 {
+            self.journal_path.metadata().unwrap().len()
+        }
+    }
+
+    fn hint(location: LocationId) -> Hint {
+        Hint {
+            receiving_player: SLOTS[1],
+            finding_player: SLOTS[0],
+            location,
+            item: ItemId(100),
+            found: false,
+            entrance: String::new(),
+            item_flags: 0,
+            status: HintStatus::Unspecified,
+        }
+    }
+
+    fn set(key: &str, operations: Vec<SetOperation>) -> Set {
+        Set {
+            key: key.into(),
+            default: None,
+            want_reply: false,
+            operations,
+        }
+    }
+
+    /// Makes a change of every kind
+    fn mutate_everything(state: &mut State) {
+        let limits = DataStorageLimits::default();
+
+        state.check_location(TEAM, SLOTS[0], LOCATIONS[0]);
+        state.add_received_items(
+            TEAM,
+            SLOTS[1],
+            vec![NetworkItem {
+                item: ItemId(100),
+                location: LOCATIONS[0],
+                player: SLOTS[0],
+                flags: 1,
+            }],
+        );
+        state.add_hint(TEAM, hint(LOCATIONS[1]));
+        state.spend_hint_points(TEAM, SLOTS[0], 5);
+        state.set_client_status(TEAM, SLOTS[0], ClientStatus::Playing);
+        state.set_alias(TEAM, SLOTS[1], Some("alias".into()));
+        state
+            .set_data_storage(
+                TEAM,
+                &set("counter", vec![SetOperation::Add(Value::int(3))]),
+                &limits,
+            )
+            .unwrap();
+        state
+            .set_data_storage(
+                TeamId(1),
+                &set("counter", vec![SetOperation::Replace(Value::str("other"))]),
+                &limits,
+            )
+            .unwrap();
+    }
+
+    /// Changes state that [`mutate_everything`] already changed
+    fn mutate_again(state: &mut State) {
+        let mut found_hint = hint(LOCATIONS[1]);
+        found_hint.found = true;
+
+        state.check_location(TEAM, SLOTS[0], LOCATIONS[1]);
+        state.add_hint(TEAM, found_hint);
+        state.set_client_status(TEAM, SLOTS[0], ClientStatus::Goal);
+        state.set_alias(TEAM, SLOTS[1], None);
+        state
+            .set_data_storage(
+                TEAM,
+                &set("counter", vec![SetOperation::Mul(Value::int(7))]),
+                &DataStorageLimits::default(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn replay_without_snapshot() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 8);
+        assert_eq!(state, setup.live);
+    }
+
+    #[test]
+    fn replay_on_top_of_an_older_snapshot() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+        setup.save_snapshot();
+        setup.journal.truncate().unwrap();
+
+        mutate_again(&mut setup.live);
+        setup.persist();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 5);
+        assert_eq!(state, setup.live);
+        assert_eq!(state, setup.replica);
+        assert_eq!(
+            state.get_data_storage_value(TEAM, "counter"),
+            Some(&Value::int(21))
+        );
+    }
+
+    #[test]
+    fn records_in_the_snapshot_are_skipped() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+        // crash after the snapshot was renamed, but before the journal was truncated
+        setup.save_snapshot();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 0);
+        assert_eq!(state, setup.live);
+
+        // records after the snapshot are still applied
+        mutate_again(&mut setup.live);
+        setup.persist();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 5);
+        assert_eq!(state, setup.live);
+    }
+
+    #[test]
+    fn incomplete_record_is_cut_off() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+
+        let complete_len = setup.journal_len();
+
+        // the crash happened after the header of the next record was written
+        setup.append_raw(&[100, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 8);
+        assert_eq!(state, setup.live);
+        assert_eq!(setup.journal_len(), complete_len);
+    }
+
+    #[test]
+    fn corrupt_record_is_cut_off() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+
+        let complete_len = setup.journal_len();
+        let mut record = Journal::open(&setup.journal_path).unwrap();
+        record
+            .append(
+                setup.replica.sequence() + 1,
+                &Mutation::SetAlias {
+                    team: TEAM,
+                    slot: SLOTS[0],
+                    alias: Some("lost".into()),
+                },
+            )
+            .unwrap();
+        // e.g. the data of a record that was never synced
+        let last = record.buffer.len() - 1;
+        record.buffer[last] ^= 0xff;
+        record.sync().unwrap();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 8);
+        assert_eq!(state, setup.live);
+        assert_eq!(setup.journal_len(), complete_len);
+
+        // new records after the cut are replayed
+        mutate_again(&mut setup.live);
+        setup.persist();
+
+        let (state, num_replayed) = setup.load().unwrap();
+
+        assert_eq!(num_replayed, 13);
+        assert_eq!(state, setup.live);
+    }
+
+    #[test]
+    fn missing_records_are_an_error() {
+        let mut setup = Setup::new();
+
+        mutate_everything(&mut setup.live);
+        setup.persist();
+        // e.g. the snapshot was deleted after the journal was compacted
+        setup.journal.truncate().unwrap();
+        mutate_again(&mut setup.live);
+        setup.persist();
+
+        assert!(setup.load().is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{Context, Result};
+use tokio::sync::oneshot;
+use tracing::{error, info};
+
+use crate::server::journal::Journal;
+use crate::server::state::{Mutation, State};
+
+/// How long the state has to stay unchanged before the journal is compacted into a snapshot
+const COMPACTION_DELAY: Duration = Duration::from_secs(10);
+/// The journal size in bytes at which it is compacted regardless of ongoing changes
+const MAX_JOURNAL_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Persists the [`State`] on a background thread.
+///
+/// Mutations are appended to the [`Journal`], which is synced once per batch.
+/// The thread keeps its own copy of the state, which is written as a snapshot
+/// once the state has settled, after which the journal starts over.
+pub struct Persistence {
+    command_sender: Sender<Command>,
+}
+
+enum Command {
+    Record(Vec<Mutation>),
+    Snapshot(oneshot::Sender<()>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+impl Persistence {
+    /// Starts persisting `state`, which has to match the snapshot and journal at `state_path`
+    pub fn start(state_path: PathBuf, state: State) -> Result<Self> {
+        let journal = Journal::open(&Journal::path_for(&state_path))?;
+        let (command_sender, command_receiver) = mpsc::channel();
+        // compact leftovers from a previous run right away
+        let compaction_deadline = (journal.size() > 0).then(Instant::now);
+        let writer = Writer {
+            state_path,
+            state,
+            journal,
+            journal_is_broken: false,
+            compaction_deadline,
+        };
+
+        thread::Builder::new()
+            .name("persistence".into())
+            .spawn(move || writer.run(command_receiver))
+            .context("failed to spawn persistence thread")?;
+
+        Ok(Self { command_sender })
+    }
+
+    pub fn record(&self, mutations: Vec<Mutation>) {
+        if self
+            .command_sender
+            .send(Command::Record(mutations))
+            .is_err()
+        {
+            error!("BUG: persistence thread is gone, mutations are lost");
+        }
+    }
+
+    /// Writes a snapshot and waits for it to finish
+    pub async fn snapshot(&self) {
+        let (done_sender, done_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(Command::Snapshot(done_sender))
+            .ok();
+        done_receiver.await.ok();
+    }
+
+    /// Writes a final snapshot and stops the background thread
+    pub async fn shutdown(self) {
+        let (done_sender, done_receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(Command::Shutdown(done_sender))
+            .ok();
+        done_receiver.await.ok();
+    }
+}
+
+struct Writer {
+    state_path: PathBuf,
+    state: State,
+    journal: Journal,
+    /// Whether the journal failed to record a mutation.
+    /// It can't be replayed past that point, so it is replaced by a snapshot as soon as possible.
+    journal_is_broken: bool,
+    compaction_deadline: Option<Instant>,
+}
+
+impl Writer {
+    fn run(mut self, command_receiver: Receiver<Command>) {
+        loop {
+            // records that are already queued are synced together
+            let command = match command_receiver.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => {
+                    self.sync_journal();
+
+                    match self.wait_for_command(&command_receiver) {
+                        Some(command) => command,
+                        None => break,
+                    }
+                }
+                Err(TryRecvError::Disconnected) => break,
+            };
+
+            match command {
+                Command::Record(mutations) => self.record(&mutations),
+                Command::Snapshot(done_sender) => {
+                    self.compact();
+                    done_sender.send(()).ok();
+                }
+                Command::Shutdown(done_sender) => {
+                    self.compact();
+                    done_sender.send(()).ok();
+                    return;
+                }
+            }
+        }
+
+        self.compact();
+    }
+
+    /// Waits for the next command while compacting the journal once it is due.
+    /// Returns `None` if the server is gone.
+    fn wait_for_command(&mut self, command_receiver: &Receiver<Command>) -> Option<Command> {
+        loop {
+            let Some(deadline) = self.compaction_deadline else {
+                return command_receiver.recv().ok();
+            };
+
+            match command_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(command) => return Some(command),
+                Err(RecvTimeoutError::Timeout) => self.compact(),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn record(&mut self, mutations: &[Mutation]) {
+        for mutation in mutations {
+            // records after a missing one would never be replayed
+            if self.journal_is_broken {
+                self.state.apply(mutation);
+                continue;
+            }
+
+            if let Err(err) = self.journal.record(&mut self.state, mutation) {
+                error!(
+                    "Failed to journal mutation {}: {err:?}",
+                    self.state.sequence()
+                );
+                self.journal_is_broken = true;
+            }
+        }
+
+        self.compaction_deadline = Some(Instant::now() + COMPACTION_DELAY);
+
+        if self.journal_is_broken || self.journal.size() >= MAX_JOURNAL_SIZE {
+            self.compact();
+        }
+    }
+
+    fn sync_journal(&mut self) {
+        if let Err(err) = self.journal.sync() {
+            error!("Failed to write the state journal: {err:?}");
+            // a partial write corrupts the records that follow it
+            self.journal_is_broken = true;
+        }
+    }
+
+    /// Writes the state as a snapshot and truncates the journal
+    fn compact(&mut self) {
+        self.compaction_deadline = None;
+        // the journal stays valid if writing the snapshot fails
+        self.sync_journal();
+
+        info!("Saving state...");
+        let start = Instant::now();
+        let result = self
+            .state
+            .save(&self.state_path)
+            .and_then(|()| self.journal.truncate());
+        let elapsed = start.elapsed();
+
+        if let Err(err) = result {
+            error!("Failed to save state after {elapsed:?}: {err:?}");
+        } else {
+            info!("Saved state successfuly after {elapsed:?}");
+            self.journal_is_broken = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aprs_proto::client::ClientStatus;
+    use aprs_proto::primitives::{LocationId, SlotId, TeamId};
+
+    use super::*;
+
+    const TEAM: TeamId = TeamId(0);
+    const SLOT: SlotId = SlotId(1);
+
+    #[test]
+    fn failed_append_is_followed_by_a_working_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("test.aprs.state");
+        let journal_path = Journal::path_for(&state_path);
+        let mut live = State::for_tests(&[SLOT], &[LocationId(10), LocationId(11)]);
+        let mut writer = Writer {
+            state_path: state_path.clone(),
+            state: live.clone(),
+            journal: Journal::open(&journal_path).unwrap(),
+            journal_is_broken: false,
+            compaction_deadline: None,
+        };
+
+        live.check_location(TEAM, SLOT, LocationId(10));
+        writer.record(&live.take_mutations());
+
+        writer.journal.fail_appends = true;
+        live.check_location(TEAM, SLOT, LocationId(11));
+        live.set_client_status(TEAM, SLOT, ClientStatus::Goal);
+        writer.record(&live.take_mutations());
+
+        // the failure forced a snapshot, which the journal continues from
+        assert!(!writer.journal_is_broken);
+        assert_eq!(writer.journal.size(), 0);
+
+        writer.journal.fail_appends = false;
+        live.set_alias(TEAM, SLOT, Some("alias".into()));
+        writer.record(&live.take_mutations());
+        writer.sync_journal();
+
+        assert_eq!(writer.state, live);
+
+        let mut state = State::try_load(&state_path).unwrap().unwrap();
+        let num_replayed = Journal::replay(&journal_path, &mut state).unwrap();
+
+        assert_eq!(num_replayed, 1);
+        assert_eq!(state, live);
+    }
+}
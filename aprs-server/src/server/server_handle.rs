@@ -34,6 +34,13 @@ impl ServerHandle {
         Ok(())
     }
 
+    /// Must not be called from async code, because it blocks until the command is queued
+    pub fn blocking_console_command(&self, command: String) -> Result<()> {
+        self.client_message_sender
+            .blocking_send(Event::ConsoleCommand(command))?;
+        Ok(())
+    }
+
     pub fn wait_for_stop(&self) -> impl Future<Output = ()> {
         self.client_message_sender.closed()
     }
@@ -1,38 +1,137 @@
 use std::borrow::Cow;
 use std::fs::File;
-use std::io;
 use std::path::Path;
+use std::{io, mem};
 
+use aprs_proto::client::ClientStatus;
+use aprs_proto::client::Set;
 use aprs_proto::primitives::{LocationId, SlotId, TeamId};
-use aprs_proto::server::NetworkItem;
-use aprs_server_core::DataStorage;
+use aprs_proto::server::{Hint, NetworkItem};
+use aprs_server_core::{DataStorage, DataStorageLimits, SetError};
 use aprs_value::Value;
 use color_eyre::eyre::{ContextCompat, Result};
 use fnv::{FnvHashMap, FnvHashSet};
 use itertools::Itertools;
-use serde::Deserialize;
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use tempfile::NamedTempFile;
-use tracing::warn;
+use tracing::error;
 
 use crate::game::MultiData;
 
-#[derive(Deserialize, Serialize)]
+/// All state of a running multiworld that has to survive a restart.
+///
+/// Every change is made through a [`Mutation`], so that it can be written to the journal
+/// and replayed on top of an older snapshot.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub struct State {
-    slot_states: FnvHashMap<SlotId, SlotState>,
-    pub data_storage: DataStorage,
+    #[serde(deserialize_with = "deserialize_slot_states")]
+    slot_states: FnvHashMap<(TeamId, SlotId), SlotState>,
+    #[serde(
+        rename = "data_storage",
+        deserialize_with = "deserialize_data_storages"
+    )]
+    data_storages: FnvHashMap<TeamId, DataStorage>,
+    #[serde(default)]
+    hints: FnvHashMap<(TeamId, SlotId), Vec<Hint>>,
+    /// The number of mutations applied since the state was created
+    #[serde(default)]
+    sequence: u64,
+    /// Mutations that have not been taken for persistence yet
+    #[serde(skip)]
+    pending_mutations: Vec<Mutation>,
+}
+
+/// A single change to the [`State`]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub enum Mutation {
+    CheckLocation {
+        team: TeamId,
+        slot: SlotId,
+        location: LocationId,
+    },
+    AddReceivedItems {
+        team: TeamId,
+        slot: SlotId,
+        items: Vec<NetworkItem>,
+    },
+    SpendHintPoints {
+        team: TeamId,
+        slot: SlotId,
+        points: u32,
+    },
+    SetClientStatus {
+        team: TeamId,
+        slot: SlotId,
+        client_status: ClientStatus,
+    },
+    SetAlias {
+        team: TeamId,
+        slot: SlotId,
+        alias: Option<String>,
+    },
+    AddHint {
+        team: TeamId,
+        hint: Hint,
+    },
+    SetDataStorage {
+        team: TeamId,
+        key: String,
+        value: Value,
+    },
 }
 
 impl State {
     pub fn new(multi_data: &MultiData) -> Self {
         let slot_states = multi_data
-            .slot_ids()
-            .map(|slot| (slot, SlotState::new(multi_data, slot)))
+            .team_ids()
+            .cartesian_product(multi_data.slot_ids().collect_vec())
+            .map(|(team, slot)| ((team, slot), SlotState::new(multi_data, slot)))
+            .collect::<FnvHashMap<_, _>>();
+
+        let hints = multi_data
+            .team_ids()
+            .flat_map(|team| {
+                multi_data
+                    .precollected_hints
+                    .iter()
+                    .map(move |(slot, hints)| ((team, *slot), hints.clone()))
+            })
             .collect::<FnvHashMap<_, _>>();
 
         Self {
             slot_states,
-            data_storage: DataStorage::new(),
+            data_storages: FnvHashMap::default(),
+            hints,
+            sequence: 0,
+            pending_mutations: Vec::new(),
+        }
+    }
+
+    /// A state of team 0 whose slots are all missing the same `locations`
+    #[cfg(test)]
+    pub fn for_tests(slots: &[SlotId], locations: &[LocationId]) -> Self {
+        let slot_states = slots
+            .iter()
+            .map(|&slot| {
+                let slot_state = SlotState {
+                    missing_locations: locations.iter().copied().collect(),
+                    checked_locations: FnvHashSet::default(),
+                    received_items: Vec::new(),
+                    spent_hint_points: 0,
+                    client_status: ClientStatus::Unknown,
+                    alias: None,
+                };
+
+                ((TeamId(0), slot), slot_state)
+            })
+            .collect();
+
+        Self {
+            slot_states,
+            data_storages: FnvHashMap::default(),
+            hints: FnvHashMap::default(),
+            sequence: 0,
+            pending_mutations: Vec::new(),
         }
     }
 
@@ -56,31 +155,208 @@ impl State {
         rmp_serde::encode::write_named(&mut encoder, self)?;
 
         let file = encoder.finish()?;
+        file.as_file().sync_all()?;
         file.persist(path)?;
 
+        // the rename has to be durable before the journal may be truncated
+        #[cfg(unix)]
+        File::open(dir)?.sync_all()?;
+
         Ok(())
     }
 
-    pub fn get_slot_state(&self, slot: SlotId) -> Option<&SlotState> {
-        self.slot_states.get(&slot)
+    pub fn get_slot_state(&self, team: TeamId, slot: SlotId) -> Option<&SlotState> {
+        self.slot_states.get(&(team, slot))
+    }
+
+    /// Each team has its own data storage
+    pub fn get_data_storage_value(&self, team: TeamId, key: &str) -> Option<&Value> {
+        self.data_storages.get(&team)?.get_raw(key)
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the mutations made since the last call
+    pub fn take_mutations(&mut self) -> Vec<Mutation> {
+        mem::take(&mut self.pending_mutations)
+    }
+
+    /// Applies the mutation without recording it, e.g. when replaying the journal
+    pub fn apply(&mut self, mutation: &Mutation) {
+        self.sequence += 1;
+
+        let (team, slot) = match *mutation {
+            Mutation::AddHint { team, ref hint } => {
+                self.insert_hint(team, hint);
+                return;
+            }
+            Mutation::SetDataStorage {
+                team,
+                ref key,
+                ref value,
+            } => {
+                self.data_storages
+                    .entry(team)
+                    .or_default()
+                    .set_raw(key.clone(), value.clone());
+                return;
+            }
+            Mutation::CheckLocation { team, slot, .. }
+            | Mutation::AddReceivedItems { team, slot, .. }
+            | Mutation::SpendHintPoints { team, slot, .. }
+            | Mutation::SetClientStatus { team, slot, .. }
+            | Mutation::SetAlias { team, slot, .. } => (team, slot),
+        };
+
+        let Some(slot_state) = self.slot_states.get_mut(&(team, slot)) else {
+            error!("BUG: mutation for missing slot {slot:?} of team {team:?}: {mutation:?}");
+            return;
+        };
+
+        match mutation {
+            Mutation::CheckLocation { location, .. } => {
+                slot_state.check_location(*location);
+            }
+            Mutation::AddReceivedItems { items, .. } => {
+                slot_state.received_items.extend(items);
+            }
+            Mutation::SpendHintPoints { points, .. } => {
+                slot_state.spent_hint_points += points;
+            }
+            Mutation::SetClientStatus { client_status, .. } => {
+                slot_state.client_status = *client_status;
+            }
+            Mutation::SetAlias { alias, .. } => {
+                slot_state.alias = alias.clone();
+            }
+            Mutation::AddHint { .. } | Mutation::SetDataStorage { .. } => {}
+        }
+    }
+
+    fn record(&mut self, mutation: Mutation) {
+        self.apply(&mutation);
+        self.pending_mutations.push(mutation);
+    }
+
+    pub fn check_location(
+        &mut self,
+        team: TeamId,
+        slot: SlotId,
+        location: LocationId,
+    ) -> CheckOutcome {
+        let is_missing = self
+            .get_slot_state(team, slot)
+            .is_some_and(|slot_state| slot_state.missing_locations.contains(&location));
+
+        if !is_missing {
+            return CheckOutcome::LocationWasChecked;
+        }
+
+        self.record(Mutation::CheckLocation {
+            team,
+            slot,
+            location,
+        });
+
+        CheckOutcome::LocationWasUnchecked
+    }
+
+    pub fn add_received_items(&mut self, team: TeamId, slot: SlotId, items: Vec<NetworkItem>) {
+        self.record(Mutation::AddReceivedItems { team, slot, items });
+    }
+
+    pub fn spend_hint_points(&mut self, team: TeamId, slot: SlotId, points: u32) {
+        self.record(Mutation::SpendHintPoints { team, slot, points });
+    }
+
+    pub fn set_client_status(&mut self, team: TeamId, slot: SlotId, client_status: ClientStatus) {
+        self.record(Mutation::SetClientStatus {
+            team,
+            slot,
+            client_status,
+        });
+    }
+
+    pub fn set_alias(&mut self, team: TeamId, slot: SlotId, alias: Option<String>) {
+        self.record(Mutation::SetAlias { team, slot, alias });
+    }
+
+    /// Returns `(original_value, new_value)`, see [`DataStorage::set`]
+    pub fn set_data_storage(
+        &mut self,
+        team: TeamId,
+        set: &Set,
+        limits: &DataStorageLimits,
+    ) -> Result<(Value, Value), SetError> {
+        let (original_value, value) = self
+            .data_storages
+            .entry(team)
+            .or_default()
+            .evaluate(set, limits)?;
+
+        self.record(Mutation::SetDataStorage {
+            team,
+            key: set.key.clone(),
+            value: value.clone(),
+        });
+
+        Ok((original_value, value))
+    }
+
+    pub fn get_hints(&self, team: TeamId, slot: SlotId) -> &[Hint] {
+        self.hints
+            .get(&(team, slot))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Adds the hint to both the finding and the receiving slot.
+    /// An existing hint for the same location is replaced.
+    pub fn add_hint(&mut self, team: TeamId, hint: Hint) {
+        self.record(Mutation::AddHint { team, hint });
     }
 
-    pub fn get_slot_state_mut(&mut self, slot: SlotId) -> Option<&mut SlotState> {
-        self.slot_states.get_mut(&slot)
+    fn insert_hint(&mut self, team: TeamId, hint: &Hint) {
+        let slots = [hint.finding_player, hint.receiving_player];
+
+        for slot in slots.into_iter().dedup() {
+            let hints = self.hints.entry((team, slot)).or_default();
+
+            match hints
+                .iter_mut()
+                .find(|existing| existing.is_same_location(hint))
+            {
+                Some(existing) => *existing = hint.clone(),
+                None => hints.push(hint.clone()),
+            }
+        }
     }
 
-    pub fn get_hints(&self, _team: TeamId, _slot: SlotId) -> Option<Value> {
-        // TODO: implement get hints
-        warn!("TODO: implement get_hints");
-        None
+    pub fn find_hint(
+        &self,
+        team: TeamId,
+        finding_slot: SlotId,
+        location: LocationId,
+    ) -> Option<&Hint> {
+        self.get_hints(team, finding_slot)
+            .iter()
+            .find(|hint| hint.finding_player == finding_slot && hint.location == location)
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 pub struct SlotState {
     missing_locations: FnvHashSet<LocationId>,
     checked_locations: FnvHashSet<LocationId>,
     received_items: Vec<NetworkItem>,
+    #[serde(default)]
+    spent_hint_points: u32,
+    #[serde(default)]
+    client_status: ClientStatus,
+    #[serde(default)]
+    alias: Option<String>,
 }
 
 impl SlotState {
@@ -103,6 +379,9 @@ impl SlotState {
             missing_locations: multi_data.location_ids(slot).collect(),
             checked_locations: FnvHashSet::default(),
             received_items: starting_inventory,
+            spent_hint_points: 0,
+            client_status: ClientStatus::Unknown,
+            alias: None,
         }
     }
 
@@ -114,7 +393,7 @@ impl SlotState {
         &self.checked_locations
     }
 
-    pub fn check_location(&mut self, location: LocationId) -> CheckOutcome {
+    fn check_location(&mut self, location: LocationId) -> CheckOutcome {
         if !self.missing_locations.remove(&location) {
             return CheckOutcome::LocationWasChecked;
         }
@@ -124,13 +403,25 @@ impl SlotState {
         CheckOutcome::LocationWasUnchecked
     }
 
-    pub fn add_received_items(&mut self, items: impl IntoIterator<Item = NetworkItem>) {
-        self.received_items.extend(items);
-    }
-
     pub fn received_items(&self) -> &[NetworkItem] {
         &self.received_items
     }
+
+    pub fn spent_hint_points(&self) -> u32 {
+        self.spent_hint_points
+    }
+
+    pub fn client_status(&self) -> ClientStatus {
+        self.client_status
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    pub fn goal_completed(&self) -> bool {
+        self.client_status == ClientStatus::Goal
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -140,11 +431,79 @@ pub enum CheckOutcome {
 }
 
 impl CheckOutcome {
-    pub fn location_was_checked(&self) -> bool {
-        matches!(self, CheckOutcome::LocationWasChecked)
-    }
-
     pub fn location_was_unchecked(&self) -> bool {
         matches!(self, CheckOutcome::LocationWasUnchecked)
     }
 }
+
+/// States saved before teams were supported are keyed by slot only and belong to team 0
+fn deserialize_slot_states<'de, D>(
+    de: D,
+) -> Result<FnvHashMap<(TeamId, SlotId), SlotState>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SlotStates {
+        Teams(FnvHashMap<(TeamId, SlotId), SlotState>),
+        Legacy(FnvHashMap<SlotId, SlotState>),
+    }
+
+    Ok(match SlotStates::deserialize(de)? {
+        SlotStates::Teams(slot_states) => slot_states,
+        SlotStates::Legacy(slot_states) => slot_states
+            .into_iter()
+            .map(|(slot, slot_state)| ((TeamId(0), slot), slot_state))
+            .collect(),
+    })
+}
+
+/// States saved before teams were supported have a single data storage, which belongs to team 0
+fn deserialize_data_storages<'de, D>(de: D) -> Result<FnvHashMap<TeamId, DataStorage>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DataStorages {
+        Teams(FnvHashMap<TeamId, DataStorage>),
+        Legacy(DataStorage),
+    }
+
+    Ok(match DataStorages::deserialize(de)? {
+        DataStorages::Teams(data_storages) => data_storages,
+        DataStorages::Legacy(data_storage) => FnvHashMap::from_iter([(TeamId(0), data_storage)]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use aprs_proto::client::SetOperation;
+
+    use super::*;
+
+    #[test]
+    fn data_storage_keys_are_scoped_to_the_team() {
+        let mut state = State::for_tests(&[SlotId(3)], &[]);
+        let set = |value: i32| Set {
+            key: "slot_3".into(),
+            default: None,
+            want_reply: false,
+            operations: vec![SetOperation::Replace(Value::int(value))],
+        };
+        let limits = DataStorageLimits::default();
+
+        state.set_data_storage(TeamId(0), &set(0), &limits).unwrap();
+        state.set_data_storage(TeamId(1), &set(1), &limits).unwrap();
+
+        assert_eq!(
+            state.get_data_storage_value(TeamId(0), "slot_3"),
+            Some(&Value::int(0))
+        );
+        assert_eq!(
+            state.get_data_storage_value(TeamId(1), "slot_3"),
+            Some(&Value::int(1))
+        );
+    }
+}
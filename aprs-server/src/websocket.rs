@@ -1,9 +1,12 @@
+use std::fmt::Display;
 use std::pin::pin;
 use std::sync::Arc;
 
+use aprs_proto::server::InvalidPacket;
 use color_eyre::eyre::{Context, Result, bail};
 use format_serde_error::SerdeError;
 use futures::SinkExt;
+use serde_json::Value as JsonValue;
 use smallvec::smallvec;
 use tokio::select;
 use tokio_stream::StreamExt;
@@ -14,7 +17,9 @@ use tracing::{debug, error};
 
 use crate::net::{Accept, ClientAddr, Listener, Stream};
 use crate::server::control::{Close, Control, ControlOrMessage, Ping, Pong};
-use crate::server::{ClientMessages, ClientToServerConnection, ServerHandle, ServerMessage};
+use crate::server::{
+    ClientMessage, ClientMessages, ClientToServerConnection, ServerHandle, ServerMessage,
+};
 
 pub fn start(listener: Listener, server_handle: ServerHandle) {
     tokio::spawn(acceptor_loop(listener, server_handle.clone()));
@@ -157,6 +162,9 @@ async fn send(
     Ok(())
 }
 
+/// Receives the next control or messages from the client.
+/// Each message of a frame is decoded independently,
+/// so an invalid message does not affect the other messages of the frame.
 async fn recv(stream: &mut WebSocketStream<Stream>) -> Result<ControlOrMessage<ClientMessages>> {
     let message = stream.next().await.transpose()?;
 
@@ -167,16 +175,21 @@ async fn recv(stream: &mut WebSocketStream<Stream>) -> Result<ControlOrMessage<C
     let message = match message {
         tungstenite::Message::Text(message) => {
             debug!("<<< {message}");
-            let messages = serde_json::from_str::<ClientMessages>(message.as_str())
-                .map_err(|err| SerdeError::new(message.to_string(), err))?;
+            let values = serde_json::from_str::<Vec<JsonValue>>(message.as_str()).map_err(|err| {
+                let invalid_packet = invalid_frame(&err);
+                let err = SerdeError::new(message.to_string(), err);
+                debug!("Failed to decode client messages: {err}");
 
-            messages.into()
+                invalid_packet
+            });
+
+            decode_messages(values).into()
         }
         tungstenite::Message::Binary(message) => {
             debug!("<<< <binary>");
-            let messages = serde_json::from_slice::<ClientMessages>(&message)?;
+            let values = serde_json::from_slice::<Vec<JsonValue>>(&message).map_err(invalid_frame);
 
-            messages.into()
+            decode_messages(values).into()
         }
         tungstenite::Message::Ping(bytes) => {
             // TODO: allow passing on Ping to the server
@@ -203,3 +216,61 @@ async fn recv(stream: &mut WebSocketStream<Stream>) -> Result<ControlOrMessage<C
 
     Ok(message)
 }
+
+pub(crate) fn decode_messages(values: Result<Vec<JsonValue>, InvalidPacket>) -> ClientMessages {
+    match values {
+        Ok(values) => values.into_iter().map(ClientMessage::decode).collect(),
+        Err(invalid_packet) => smallvec![Err(invalid_packet)],
+    }
+}
+
+fn invalid_frame(err: impl Display) -> InvalidPacket {
+    InvalidPacket::cmd(None, format!("Failed to decode packet: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn every_packet_of_a_batch_is_decoded_on_its_own() {
+        let messages = decode_messages(Ok(vec![
+            json!({"cmd": "Say", "text": "hi"}),
+            json!({"cmd": "Dance"}),
+            json!({"cmd": "Sync"}),
+            json!({"cmd": "Say", "text": 1}),
+            json!({"text": "hi"}),
+        ]));
+        let results = messages
+            .iter()
+            .map(|message| match message {
+                Ok(message) => Ok(message.cmd()),
+                Err(invalid_packet) => Err(invalid_packet.original_cmd.as_deref()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            results,
+            [
+                Ok("Say"),
+                Err(Some("Dance")),
+                Ok("Sync"),
+                Err(Some("Say")),
+                Err(None)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_frames_are_a_single_invalid_packet() {
+        let messages = decode_messages(Err(invalid_frame("expected `,` or `]`")));
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].as_ref().unwrap_err().text,
+            "Failed to decode packet: expected `,` or `]`"
+        );
+    }
+}
@@ -218,7 +218,7 @@ impl<'de> de::Visitor<'de> for ValueVisitor {
             let mut dict = dict.write();
 
             while let Some((key, value)) = map.next_entry::<Value, Value>()? {
-                dict.insert(key, value);
+                dict.insert(key, value).map_err(de::Error::custom)?;
             }
         }
 
@@ -61,6 +61,39 @@ impl Dict {
         Ok(())
     }
 
+    /// Returns a copy of the dict that doesn't share any containers with it
+    pub fn deep_copy(&self) -> Dict {
+        let this = self.0.read();
+        let inner = Inner {
+            value_dict: this
+                .value_dict
+                .iter()
+                .map(|(key, value)| (key.clone(), value.deep_copy()))
+                .collect(),
+            int_dict: this
+                .int_dict
+                .iter()
+                .map(|(key, value)| (*key, value.deep_copy()))
+                .collect(),
+        };
+
+        Self(Arc::new(RwLock::new(inner)))
+    }
+
+    /// Returns a new dict with the entries of both dicts.
+    /// Entries of `other` take precedence.
+    pub fn merge(&self, other: &Dict) -> Result<Dict> {
+        let dict = Dict::try_from_iter(
+            self.read()
+                .iter()
+                .map(|(key, value)| (Value::from(key), value.clone())),
+        )?;
+
+        dict.update(other)?;
+
+        Ok(dict)
+    }
+
     pub fn try_from_iter<K, V, I>(iter: I) -> Result<Self>
     where
         K: Into<Value>,
@@ -98,6 +131,13 @@ impl Inner {
         }
     }
 
+    fn remove(&mut self, key: &Value) -> Option<Value> {
+        match &key {
+            Value::Int(Int::I64(key)) => self.int_dict.shift_remove(key),
+            _ => self.value_dict.shift_remove(key),
+        }
+    }
+
     fn iter(&self) -> impl Iterator<Item = (Key<'_>, &Value)> {
         let value_iter = self
             .value_dict
@@ -204,6 +244,10 @@ impl<'a> WriteDictGuard<'a> {
     pub fn extend(&mut self, items: impl IntoIterator<Item = (Value, Value)>) {
         self.inner.extend(items);
     }
+
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        self.inner.remove(key)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -52,14 +52,10 @@ impl Pow<&Int> for Float {
     type Output = Result<Float>;
 
     fn pow(self, exp: &Int) -> Self::Output {
-        let exp = i32::try_from(exp).context("exponent too big")?;
-        let value = self.0.powi(exp);
+        // Python converts the exponent to a float as well
+        let exp = Float::try_from(exp).context("exponent too big")?;
 
-        if self.is_finite() && value.is_infinite() {
-            bail!("Numerical result out of range")
-        }
-
-        Ok(Float(value))
+        self.pow(exp)
     }
 }
 
@@ -180,8 +176,8 @@ impl PartialEq<Int> for Float {
 impl PartialEq<Bool> for Float {
     fn eq(&self, other: &Bool) -> bool {
         match **other {
-            true => self.0.is_zero(),
-            false => self.0.is_one(),
+            true => self.0.is_one(),
+            false => self.0.is_zero(),
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
-use std::ops::{Add, BitAnd, BitOr, Mul, Rem, Sub};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Rem, Shl, Shr, Sub};
 
 use eyre::{Context, ContextCompat, Result, bail};
 use num::traits::Pow;
@@ -16,6 +16,10 @@ pub enum Int {
 }
 
 impl Int {
+    /// The maximum number of bits of results that can grow out of proportion to their operands,
+    /// e.g. a left shift by a huge count, which would otherwise exhaust the memory.
+    pub const MAX_BITS: u64 = 4096;
+
     pub fn is_i64(&self) -> bool {
         matches!(self, Self::I64(_))
     }
@@ -77,6 +81,15 @@ impl Int {
         }
     }
 
+    /// The number of bits needed to represent the absolute value of `self`
+    pub fn bits(&self) -> u64 {
+        match *self {
+            Int::I64(n) => u64::from(u64::BITS - n.unsigned_abs().leading_zeros()),
+            Int::I128(n) => u64::from(u128::BITS - n.unsigned_abs().leading_zeros()),
+            Int::BigInt(ref n) => n.bits(),
+        }
+    }
+
     pub fn to_u32(&self) -> Option<u32> {
         match *self {
             Int::I64(n) => n.to_u32(),
@@ -166,6 +179,51 @@ impl Int {
         }
     }
 
+    /// Returns `self` if it has at most [`Int::MAX_BITS`] bits
+    pub fn check_bits(self) -> Result<Int> {
+        if self.bits() > Self::MAX_BITS {
+            bail!("integer has more than {} bits", Self::MAX_BITS);
+        }
+
+        Ok(self)
+    }
+
+    /// Shifts `self` left by `count` bits.
+    /// Results with more than [`Int::MAX_BITS`] bits are an error.
+    /// Negative counts are an error, just like in Python.
+    pub fn shift_left(&self, count: &Int) -> Result<Int> {
+        if count.is_negative() {
+            bail!("negative shift count");
+        }
+
+        if self.is_zero() {
+            return Ok(Int::I64(0));
+        }
+
+        let count = count
+            .to_u32()
+            .filter(|&count| self.bits() + u64::from(count) <= Self::MAX_BITS)
+            .context("shift count too big")?;
+
+        Ok(self.to_big_int().shl(count).minimize())
+    }
+
+    /// Shifts `self` right by `count` bits, rounding towards negative infinity.
+    /// Negative counts are an error, just like in Python.
+    pub fn shift_right(&self, count: &Int) -> Result<Int> {
+        if count.is_negative() {
+            bail!("negative shift count");
+        }
+
+        let Some(count) = count.to_u32() else {
+            let n = if self.is_negative() { -1 } else { 0 };
+
+            return Ok(Int::I64(n));
+        };
+
+        Ok(self.to_big_int().shr(count).minimize())
+    }
+
     pub fn modulo(&self, divisor: &Self) -> Result<Self> {
         let remainder = self.rem(divisor)?;
 
@@ -233,7 +291,15 @@ impl Pow<&Int> for &Int {
 
         let exp = exp.to_u32().context("exponent too big")?;
 
-        Ok(Value::Int(self.powi_positive_exp(exp)))
+        // the result has at least `(bits - 1) * exp + 1` bits
+        if self.bits().saturating_sub(1) * u64::from(exp) >= Int::MAX_BITS {
+            bail!("result of `pow` is too big");
+        }
+
+        self.powi_positive_exp(exp)
+            .check_bits()
+            .context("result of `pow` is too big")
+            .map(Value::Int)
     }
 }
 
@@ -799,6 +865,65 @@ fn or_big_int(a: impl Into<BigInt>, b: impl Into<BigInt>) -> Int {
     Int::BigInt(a.bitor(b))
 }
 
+impl BitXor<Int> for Int {
+    type Output = Int;
+
+    fn bitxor(self, rhs: Int) -> Self::Output {
+        match (self, rhs) {
+            (Int::I64(a), Int::I64(b)) => xor_i64(a, b),
+            (Int::I64(a), Int::I128(b)) => xor_i128(a, b),
+            (Int::I64(a), Int::BigInt(b)) => xor_big_int(a, b),
+            (Int::I128(a), Int::I64(b)) => xor_i128(a, b),
+            (Int::I128(a), Int::I128(b)) => xor_i128(a, b),
+            (Int::I128(a), Int::BigInt(b)) => xor_big_int(a, b),
+            (Int::BigInt(a), Int::I64(b)) => xor_big_int(a, b),
+            (Int::BigInt(a), Int::I128(b)) => xor_big_int(a, b),
+            (Int::BigInt(a), Int::BigInt(b)) => xor_big_int(a, b),
+        }
+    }
+}
+
+// TODO: optimize / prevent unnecessary clones
+impl BitXor<&Int> for &Int {
+    type Output = Int;
+
+    fn bitxor(self, rhs: &Int) -> Self::Output {
+        match (self, rhs) {
+            (Int::I64(a), Int::I64(b)) => xor_i64(*a, *b),
+            (Int::I64(a), Int::I128(b)) => xor_i128(*a, *b),
+            (Int::I64(a), Int::BigInt(b)) => xor_big_int(*a, b.clone()),
+            (Int::I128(a), Int::I64(b)) => xor_i128(*a, *b),
+            (Int::I128(a), Int::I128(b)) => xor_i128(*a, *b),
+            (Int::I128(a), Int::BigInt(b)) => xor_big_int(*a, b.clone()),
+            (Int::BigInt(a), Int::I64(b)) => xor_big_int(a.clone(), *b),
+            (Int::BigInt(a), Int::I128(b)) => xor_big_int(a.clone(), *b),
+            (Int::BigInt(a), Int::BigInt(b)) => xor_big_int(a.clone(), b.clone()),
+        }
+    }
+}
+
+fn xor_i64(a: impl Into<i64>, b: impl Into<i64>) -> Int {
+    let a = a.into();
+    let b = b.into();
+
+    Int::I64(a.bitxor(b))
+}
+
+fn xor_i128(a: impl Into<i128>, b: impl Into<i128>) -> Int {
+    let a = a.into();
+    let b = b.into();
+
+    Int::I128(a.bitxor(b))
+}
+
+// TODO: optimization: only one operand needs to be converted BigInt
+fn xor_big_int(a: impl Into<BigInt>, b: impl Into<BigInt>) -> Int {
+    let a = a.into();
+    let b = b.into();
+
+    Int::BigInt(a.bitxor(b))
+}
+
 impl PartialEq for Int {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
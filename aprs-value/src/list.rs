@@ -70,6 +70,24 @@ impl List {
         })
     }
 
+    /// Returns a copy of the list that doesn't share any containers with it
+    pub fn deep_copy(&self) -> List {
+        self.iter().map(|value| value.deep_copy()).collect()
+    }
+
+    /// Removes the first value equal to `value` and returns whether one was found
+    pub fn remove_value(&self, value: &Value) -> bool {
+        let mut list = self.0.write();
+
+        let Some(index) = list.iter().position(|item| item == value) else {
+            return false;
+        };
+
+        list.remove(index);
+
+        true
+    }
+
     pub fn extend(&self, values: Vec<Value>) {
         self.0.write().extend(values);
     }
@@ -252,7 +270,7 @@ impl fmt::Debug for List {
 
 impl PartialEq for List {
     fn eq(&self, other: &Self) -> bool {
-        self.iter().zip(other).all(|(v1, v2)| v1 == v2)
+        self.len() == other.len() && self.iter().zip(other).all(|(v1, v2)| v1 == v2)
     }
 }
 
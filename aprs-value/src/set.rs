@@ -56,6 +56,60 @@ impl Set {
 
         Ok(set)
     }
+
+    /// Returns a copy of the set.
+    /// Set values are hashable and therefore immutable, so they don't need to be copied.
+    pub fn deep_copy(&self) -> Set {
+        let this = self.0.read();
+        let inner = Inner {
+            value_set: this.value_set.clone(),
+            int_set: this.int_set.clone(),
+        };
+
+        Self(Arc::new(RwLock::new(inner)))
+    }
+
+    /// Returns a new set with the values contained in both sets
+    pub fn intersection(&self, other: &Set) -> Result<Set> {
+        let other = other.read();
+        let values = self.read().iter().map(Value::from).collect::<Vec<_>>();
+
+        Set::try_from_iter(values.into_iter().filter(|value| other.contains(value)))
+    }
+
+    /// Returns a new set with the values contained in either set
+    pub fn union(&self, other: &Set) -> Result<Set> {
+        let mut values = self.read().iter().map(Value::from).collect::<Vec<_>>();
+
+        values.extend(other.read().iter().map(Value::from));
+
+        Set::try_from_iter(values)
+    }
+
+    /// Returns a new set with the values contained in exactly one of the sets
+    pub fn symmetric_difference(&self, other: &Set) -> Result<Set> {
+        let this = self.read();
+        let other = other.read();
+        let only_this = this
+            .iter()
+            .map(Value::from)
+            .filter(|value| !other.contains(value));
+        let only_other = other
+            .iter()
+            .map(Value::from)
+            .filter(|value| !this.contains(value));
+
+        Set::try_from_iter(only_this.chain(only_other))
+    }
+
+    /// Returns `true` if every value of `self` is contained in `other`
+    fn is_subset(&self, other: &Set) -> bool {
+        let other = other.read();
+
+        self.read()
+            .iter()
+            .all(|value| other.contains(&value.into()))
+    }
 }
 
 impl Inner {
@@ -78,6 +132,20 @@ impl Inner {
         Ok(())
     }
 
+    fn contains(&self, key: &Value) -> bool {
+        match key {
+            Value::Int(Int::I64(key)) => self.int_set.contains(key),
+            _ => self.value_set.contains(key),
+        }
+    }
+
+    fn remove(&mut self, key: &Value) -> bool {
+        match key {
+            Value::Int(Int::I64(key)) => self.int_set.shift_remove(key),
+            _ => self.value_set.shift_remove(key),
+        }
+    }
+
     fn iter(&self) -> impl Iterator<Item = Item<'_>> {
         let value_set = self.value_set.iter().map(Item::Value);
         let int_set = self.int_set.iter().copied().map(Item::Int64);
@@ -86,7 +154,7 @@ impl Inner {
     }
 
     fn len(&self) -> usize {
-        self.value_set.len()
+        self.value_set.len() + self.int_set.len()
     }
 
     fn extend(&mut self, items: impl IntoIterator<Item = Value>) {
@@ -141,6 +209,10 @@ impl<'a> ReadSetGuard<'a> {
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    pub fn contains(&self, key: &Value) -> bool {
+        self.inner.contains(key)
+    }
 }
 
 pub struct WriteSetGuard<'a> {
@@ -162,6 +234,11 @@ impl<'a> WriteSetGuard<'a> {
         self.inner.insert(key)
     }
 
+    /// Removes `key` and returns whether it was present
+    pub fn remove(&mut self, key: &Value) -> bool {
+        self.inner.remove(key)
+    }
+
     pub fn extend(&mut self, items: impl IntoIterator<Item = Value>) {
         self.inner.extend(items)
     }
@@ -182,16 +259,16 @@ impl From<Item<'_>> for Value {
     }
 }
 
+/// Sets are ordered by the subset relation, like in Python
 impl PartialOrd for Set {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         let len_this = self.read().len();
         let len_other = other.read().len();
-        let ordering = len_this.cmp(&len_other);
 
-        if ordering.is_ne() {
-            return Some(ordering);
+        match len_this.cmp(&len_other) {
+            Ordering::Less => self.is_subset(other).then_some(Ordering::Less),
+            Ordering::Equal => (self == other).then_some(Ordering::Equal),
+            Ordering::Greater => other.is_subset(self).then_some(Ordering::Greater),
         }
-
-        (self == other).then_some(Ordering::Equal)
     }
 }
@@ -45,6 +45,11 @@ impl Tuple {
         &self.0
     }
 
+    /// Returns a copy of the tuple that doesn't share any containers with it
+    pub fn deep_copy(&self) -> Tuple {
+        self.iter().map(Value::deep_copy).collect()
+    }
+
     pub fn partial_cmp(&self, other: &Tuple) -> Result<Option<Ordering>> {
         for ab in self.iter().zip_longest(other) {
             match ab {
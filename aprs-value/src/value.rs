@@ -1,9 +1,9 @@
 use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::ops::{Add, BitAnd, BitOr, Mul, Rem, Sub};
+use std::ops::{Add, Mul, Sub};
 
-use eyre::{Context, Result, bail};
+use eyre::{Context, ContextCompat, Result, bail, ensure, eyre};
 use num::traits::Pow;
 use tracing::error;
 
@@ -12,6 +12,9 @@ use crate::{Bool, Callable, Dict, Float, Int, List, Set, Str, Tuple};
 #[cfg(test)]
 mod tests;
 
+/// The maximum length in bytes of strings created by repetition
+const MAX_REPEATED_STR_LEN: usize = 1024 * 1024;
+
 pub enum Value {
     Dict(Dict),
     List(List),
@@ -204,11 +207,52 @@ impl Value {
             _ => Option::None,
         }
     }
+
+    /// Returns a copy of `self` that doesn't share any dicts, lists or sets with it.
+    /// `clone` only copies the handles of containers, so mutating the clone
+    /// also mutates the original.
+    pub fn deep_copy(&self) -> Value {
+        match self {
+            Value::Dict(dict) => Value::Dict(dict.deep_copy()),
+            Value::List(list) => Value::List(list.deep_copy()),
+            Value::Set(set) => Value::Set(set.deep_copy()),
+            Value::Tuple(tuple) => Value::Tuple(tuple.deep_copy()),
+            Value::Str(_)
+            | Value::Int(_)
+            | Value::Float(_)
+            | Value::Bool(_)
+            | Value::Callable(_)
+            | Value::None(_) => self.clone(),
+        }
+    }
+
+    /// Converts bools to ints and returns other values unchanged,
+    /// since Python treats `bool` as a subclass of `int` in arithmetic.
+    fn bool_to_int(&self) -> Value {
+        match self {
+            Value::Bool(bool) => Value::Int(Int::from(**bool)),
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns the values Python would yield when iterating over `self`.
+    /// Dicts yield their keys and strings their characters.
+    pub fn to_vec(&self) -> Result<Vec<Value>> {
+        Ok(match self {
+            Value::List(list) => list.iter().collect(),
+            Value::Tuple(tuple) => tuple.iter().cloned().collect(),
+            Value::Set(set) => set.read().iter().map(Value::from).collect(),
+            Value::Dict(dict) => dict.read().iter().map(|(key, _)| key.into()).collect(),
+            Value::Str(str) => str.chars().map(Value::str).collect(),
+            _ => bail!("{} is not iterable", self.type_name()),
+        })
+    }
 }
 
 impl Value {
     pub fn add(&self, rhs: &Value) -> Result<Value> {
         match (self, rhs) {
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => self.bool_to_int().add(&rhs.bool_to_int()),
             (Value::Int(a), Value::Int(b)) => Self::add_int(a, b),
             (Value::Float(a), Value::Float(b)) => Self::add_float(a, b),
             (Value::Int(a), Value::Float(b)) => Self::add_float(a, b),
@@ -239,6 +283,7 @@ impl Value {
 
     pub fn sub(&self, rhs: &Value) -> Result<Value> {
         match (self, rhs) {
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => self.bool_to_int().sub(&rhs.bool_to_int()),
             (Value::Int(a), Value::Int(b)) => Self::sub_int(a, b),
             (Value::Float(a), Value::Float(b)) => Self::sub_float(a, b),
             (Value::Int(a), Value::Float(b)) => Self::sub_float(a, b),
@@ -267,43 +312,45 @@ impl Value {
 
     pub fn mul(&self, rhs: &Value) -> Result<Value> {
         match (self, rhs) {
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => self.bool_to_int().mul(&rhs.bool_to_int()),
             (Value::Int(a), Value::Int(b)) => Self::mul_int(a, b),
             (Value::Float(a), Value::Float(b)) => Self::mul_float(a, b),
             (Value::Int(a), Value::Float(b)) => Self::mul_float(a, b),
             (Value::Float(a), Value::Int(b)) => Self::mul_float(a, b),
-            (Value::Str(a), Value::Int(b)) => {
-                let repeats =
-                    isize::try_from(b).context("number of string repetitions is too big")?;
-
-                if repeats <= 0 {
-                    return Ok(Value::str(""));
-                }
+            (Value::Str(a), Value::Int(b)) => Self::repeat_str(a, b),
+            (Value::Int(a), Value::Str(b)) => Self::repeat_str(b, a),
+            _ => bail!("Can't `mul` {self:?} and {rhs:?}"),
+        }
+    }
 
-                let repeats =
-                    usize::try_from(repeats).context("number of string repetitions is too big")?;
+    fn mul_int(a: &Int, b: &Int) -> Result<Value> {
+        // the result has at least `a.bits() + b.bits() - 1` bits
+        if !a.is_zero() && !b.is_zero() && a.bits() + b.bits() - 1 > Int::MAX_BITS {
+            bail!("result of `mul` is too big");
+        }
 
-                Ok(Value::str(a.repeat(repeats)))
-            }
-            (Value::Int(a), Value::Str(b)) => {
-                let repeats =
-                    isize::try_from(a).context("number of string repetitions is too big")?;
+        a.mul(b)
+            .check_bits()
+            .context("result of `mul` is too big")
+            .map(Value::Int)
+    }
 
-                if repeats <= 0 {
-                    return Ok(Value::str(""));
-                }
+    fn repeat_str(s: &str, repeats: &Int) -> Result<Value> {
+        let repeats =
+            isize::try_from(repeats).context("number of string repetitions is too big")?;
 
-                let repeats =
-                    usize::try_from(repeats).context("number of string repetitions is too big")?;
+        if repeats <= 0 {
+            return Ok(Value::str(""));
+        }
 
-                Ok(Value::str(b.repeat(repeats)))
-            }
+        let repeats =
+            usize::try_from(repeats).context("number of string repetitions is too big")?;
 
-            _ => bail!("Can't `mul` {self:?} and {rhs:?}"),
+        if s.len().saturating_mul(repeats) > MAX_REPEATED_STR_LEN {
+            bail!("result of string repetition is too long");
         }
-    }
 
-    fn mul_int(a: &Int, b: &Int) -> Result<Value> {
-        Ok(a.mul(b).into())
+        Ok(Value::str(s.repeat(repeats)))
     }
 
     fn mul_float<A, B>(a: A, b: B) -> Result<Value>
@@ -321,6 +368,9 @@ impl Value {
 
     pub fn pow(&self, rhs: &Value) -> Result<Value> {
         Ok(match (self, rhs) {
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => {
+                return self.bool_to_int().pow(&rhs.bool_to_int());
+            }
             (Value::Int(a), Value::Int(b)) => return a.pow(b),
             (Value::Int(a), Value::Float(b)) => return a.pow(*b),
             (Value::Float(a), Value::Int(b)) => Value::Float(a.pow(b)?),
@@ -331,6 +381,9 @@ impl Value {
 
     pub fn modulo(&self, rhs: &Value) -> Result<Value> {
         Ok(match (self, rhs) {
+            (Value::Bool(_), _) | (_, Value::Bool(_)) => {
+                return self.bool_to_int().modulo(&rhs.bool_to_int());
+            }
             (Value::Int(a), Value::Int(b)) => Value::Int(a.modulo(b)?),
             (Value::Int(a), Value::Float(b)) => Value::Float(Float::try_from(a)?.modulo(b)?),
             (Value::Float(a), Value::Int(b)) => Value::Float(a.modulo(&Float::try_from(b)?)?),
@@ -343,6 +396,7 @@ impl Value {
         match self {
             Self::Int(n) => Ok(Self::Int(n.clone())),
             Self::Float(n) => n.floor().map(Self::Int),
+            Self::Bool(_) => Ok(self.bool_to_int()),
             _ => bail!("Can't `floor` {self:?}"),
         }
     }
@@ -351,6 +405,7 @@ impl Value {
         match self {
             Self::Int(n) => Ok(Self::Int(n.clone())),
             Self::Float(n) => n.ceil().map(Self::Int),
+            Self::Bool(_) => Ok(self.bool_to_int()),
             _ => bail!("Can't `ceil` {self:?}"),
         }
     }
@@ -385,47 +440,161 @@ impl Value {
 
     pub fn and(&self, rhs: &Value) -> Result<Value> {
         match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Ok(Self::Int(a.bitand(b))),
             (Value::Bool(a), Value::Bool(b)) => Ok((**a && **b).into()),
-            _ => bail!("Can't `and` {self:?} and {rhs:?}"),
+            (Value::Set(a), Value::Set(b)) => a.intersection(b).map(Value::Set),
+            _ => match (self.bool_to_int(), rhs.bool_to_int()) {
+                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a & b)),
+                _ => bail!("Can't `and` {self:?} and {rhs:?}"),
+            },
         }
     }
 
     pub fn or(&self, rhs: &Value) -> Result<Value> {
         match (self, rhs) {
-            (Value::Int(a), Value::Int(b)) => Ok(Self::Int(a.bitor(b))),
             (Value::Bool(a), Value::Bool(b)) => Ok((**a || **b).into()),
-            _ => bail!("Can't `or` {self:?} and {rhs:?}"),
+            (Value::Set(a), Value::Set(b)) => a.union(b).map(Value::Set),
+            (Value::Dict(a), Value::Dict(b)) => a.merge(b).map(Value::Dict),
+            _ => match (self.bool_to_int(), rhs.bool_to_int()) {
+                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a | b)),
+                _ => bail!("Can't `or` {self:?} and {rhs:?}"),
+            },
         }
     }
 
-    fn xor(&self, _rhs: &Value) -> Result<Value> {
-        todo!()
+    pub fn xor(&self, rhs: &Value) -> Result<Value> {
+        match (self, rhs) {
+            (Value::Bool(a), Value::Bool(b)) => Ok((**a != **b).into()),
+            (Value::Set(a), Value::Set(b)) => a.symmetric_difference(b).map(Value::Set),
+            _ => match (self.bool_to_int(), rhs.bool_to_int()) {
+                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a ^ b)),
+                _ => bail!("Can't `xor` {self:?} and {rhs:?}"),
+            },
+        }
     }
 
-    fn left_shift(&self, _rhs: &Value) -> Result<Value> {
-        todo!()
+    pub fn left_shift(&self, rhs: &Value) -> Result<Value> {
+        match (self.bool_to_int(), rhs.bool_to_int()) {
+            (Value::Int(a), Value::Int(b)) => a.shift_left(&b).map(Value::Int),
+            _ => bail!("Can't `left_shift` {self:?} and {rhs:?}"),
+        }
     }
 
-    fn right_shift(&self, _rhs: &Value) -> Result<Value> {
-        todo!()
+    pub fn right_shift(&self, rhs: &Value) -> Result<Value> {
+        match (self.bool_to_int(), rhs.bool_to_int()) {
+            (Value::Int(a), Value::Int(b)) => a.shift_right(&b).map(Value::Int),
+            _ => bail!("Can't `right_shift` {self:?} and {rhs:?}"),
+        }
     }
 
-    fn remove(&self, _rhs: &Value) -> Result<Value> {
-        todo!()
+    /// Removes the first occurrence of `value` from a list or `value` from a set.
+    /// Like Archipelago's `remove` operation, a value missing from a list is ignored,
+    /// but a value missing from a set is an error.
+    pub fn remove(&self, value: &Value) -> Result<()> {
+        match self {
+            Value::List(list) => {
+                list.remove_value(value);
+            }
+            Value::Set(set) => {
+                ensure!(value.is_hashable(), "{} is unhashable", value.type_name());
+                ensure!(set.write().remove(value), "{value:?} is not in the set");
+            }
+            _ => bail!("Can't `remove` from {}", self.type_name()),
+        }
+
+        Ok(())
     }
 
-    pub fn pop(&self, rhs: &Value) -> Result<Option<Value>> {
-        let list = self.as_list()?;
+    /// Removes the entry at index or key `key` from a list or dict.
+    /// Like Archipelago's `pop` operation, indices past the end of a list and
+    /// missing keys are ignored. Negative indices count from the end of the list.
+    pub fn pop(&self, key: &Value) -> Result<Option<Value>> {
+        match self {
+            Value::List(list) => {
+                let Value::Int(index) = key.bool_to_int() else {
+                    bail!("list indices must be Int, not {}", key.type_name());
+                };
+                let len = Int::from(list.len());
+
+                if matches!(index.cmp(&len), Some(Ordering::Greater | Ordering::Equal)) {
+                    return Ok(Option::None);
+                }
+
+                let index = if index.is_negative() {
+                    &len + &index
+                } else {
+                    index
+                };
+                let index = index.to_usize().context("pop index out of range")?;
+
+                Ok(list.remove(index))
+            }
+            Value::Dict(dict) => {
+                ensure!(key.is_hashable(), "{} is unhashable", key.type_name());
 
-        list.remove(rhs)
+                Ok(dict.write().remove(key))
+            }
+            _ => bail!("Can't `pop` from {}", self.type_name()),
+        }
     }
 
-    pub fn update(&self, dict: &Value) -> Result<()> {
-        let this = self.as_dict()?;
-        let dict = dict.as_dict()?;
+    /// Adds the entries of `entries` to a list, dict or set.
+    /// Like Archipelago's `update` operation, a list is only extended
+    /// by the entries it doesn't contain yet.
+    pub fn update(&self, entries: &Value) -> Result<()> {
+        match self {
+            Value::List(list) => {
+                let existing = list.iter().collect::<Vec<_>>();
+                let entries = entries.to_vec()?;
+
+                if let Some(value) = existing.iter().chain(&entries).find(|v| !v.is_hashable()) {
+                    bail!("{} is unhashable", value.type_name());
+                }
+
+                let entries = entries
+                    .into_iter()
+                    .filter(|entry| !existing.contains(entry))
+                    .collect();
+
+                list.extend(entries);
+
+                Ok(())
+            }
+            Value::Dict(dict) => {
+                if let Value::Dict(entries) = entries {
+                    return dict.update(entries);
+                }
+
+                let entries = entries
+                    .to_vec()?
+                    .iter()
+                    .map(|entry| {
+                        let pair = entry.to_vec()?;
+
+                        <[Value; 2]>::try_from(pair).map_err(|pair| {
+                            eyre!("dict update entry has length {}; 2 is required", pair.len())
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let mut dict = dict.write();
+
+                for [key, value] in entries {
+                    dict.insert(key, value)?;
+                }
 
-        this.update(dict)
+                Ok(())
+            }
+            Value::Set(set) => {
+                let entries = entries.to_vec()?;
+                let mut set = set.write();
+
+                for entry in entries {
+                    set.insert(entry)?;
+                }
+
+                Ok(())
+            }
+            _ => bail!("Can't `update` {}", self.type_name()),
+        }
     }
 
     pub fn partial_cmp(&self, other: &Value) -> Result<Option<Ordering>> {
@@ -1,4 +1,4 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::sync::LazyLock;
 
 use itertools::Itertools;
@@ -10,9 +10,9 @@ use serde::{Deserialize, Serialize};
 use strum::VariantArray;
 
 use crate::int::Minimize;
-use crate::{Dict, List, Set, Tuple, Value};
+use crate::{Dict, Int, List, Set, Tuple, Value};
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
 #[serde(rename_all = "lowercase")]
 enum PythonValue {
     Int(BigInt),
@@ -97,6 +97,26 @@ impl PythonValue {
             _ => panic!("not a list: {self:?}"),
         }
     }
+
+    /// Sorts the entries of dicts and sets, since their iteration order
+    /// differs between Python and Rust.
+    fn normalized(self) -> PythonValue {
+        match self {
+            Self::List(value) => Self::List(value.into_iter().map(Self::normalized).collect()),
+            Self::Tuple(value) => Self::Tuple(value.into_iter().map(Self::normalized).collect()),
+            Self::Dict(value) => Self::Dict(
+                value
+                    .into_iter()
+                    .map(|(k, v)| (k.normalized(), v.normalized()))
+                    .sorted()
+                    .collect(),
+            ),
+            Self::Set(value) => {
+                Self::Set(value.into_iter().map(Self::normalized).sorted().collect())
+            }
+            value => value,
+        }
+    }
 }
 
 fn slice_to_python(slice: &[PythonValue]) -> String {
@@ -176,6 +196,12 @@ impl From<f64> for PythonValue {
     }
 }
 
+impl From<bool> for PythonValue {
+    fn from(value: bool) -> Self {
+        PythonValue::Bool(value)
+    }
+}
+
 impl From<&str> for PythonValue {
     fn from(value: &str) -> Self {
         PythonValue::Str(String::from(value))
@@ -232,11 +258,58 @@ macro_rules! list {
     };
 }
 
+macro_rules! set {
+    [$($expr:expr),*$(,)?] => {
+        PythonValue::Set(vec![$($expr.into()),*])
+    };
+}
+
+macro_rules! dict {
+    {$($key:expr => $value:expr),*$(,)?} => {
+        PythonValue::Dict(vec![$(($key.into(), $value.into())),*])
+    };
+}
+
+/// The helpers Archipelago's `MultiServer` uses for the `remove`, `pop` and `update` operations
+const PRELUDE: &CStr = cr#"
+import math
+
+def remove_from_list(container, value):
+    try:
+        container.remove(value)
+    except ValueError:
+        pass
+    return container
+
+def pop_from_container(container, value):
+    if isinstance(container, list) and isinstance(value, int) and len(container) <= value:
+        return container
+
+    if isinstance(container, dict) and value not in container:
+        return container
+
+    container.pop(value)
+    return container
+
+def update_container_unique(container, entries):
+    if isinstance(container, list):
+        existing_container_as_set = set(container)
+        container.extend([entry for entry in entries if entry not in existing_container_as_set])
+    else:
+        container.update(entries)
+    return container
+"#;
+
 fn eval_python(code: &str) -> Result<PythonValue, String> {
     let code = CString::new(code).unwrap();
 
     Python::attach(|py| {
-        py.eval(&code, None, None)
+        let globals = PyDict::new(py);
+
+        py.run(PRELUDE, Some(&globals), None)
+            .expect("failed to run prelude");
+
+        py.eval(&code, Some(&globals), None)
             .map(pyo3_value_to_python_value)
             .map_err(|err| err.to_string())
     })
@@ -333,6 +406,28 @@ enum UnaryOp {
     Ceil,
 }
 
+impl UnaryOp {
+    fn to_python(&self, value: &PythonValue) -> String {
+        let value = value.to_python();
+
+        match self {
+            UnaryOp::Floor => format!("math.floor({value})"),
+            UnaryOp::Ceil => format!("math.ceil({value})"),
+        }
+    }
+
+    fn eval_rust(&self, value: &PythonValue) -> Result<PythonValue, String> {
+        let value = value.to_rust();
+
+        let result = match self {
+            UnaryOp::Floor => value.floor(),
+            UnaryOp::Ceil => value.ceil(),
+        };
+
+        result.map(PythonValue::from).map_err(|err| err.to_string())
+    }
+}
+
 #[derive(VariantArray)]
 enum BinaryOp {
     Add,
@@ -343,9 +438,12 @@ enum BinaryOp {
     Max,
     And,
     Or,
-    // TODO: Pop,
-    // TODO: Update,
-    // TODO: Remove,
+    Xor,
+    LeftShift,
+    RightShift,
+    Pop,
+    Update,
+    Remove,
 }
 
 impl BinaryOp {
@@ -362,6 +460,12 @@ impl BinaryOp {
             BinaryOp::Max => format!("max({lhs}, {rhs})"),
             BinaryOp::And => format!("({lhs}) & ({rhs})"),
             BinaryOp::Or => format!("({lhs}) | ({rhs})"),
+            BinaryOp::Xor => format!("({lhs}) ^ ({rhs})"),
+            BinaryOp::LeftShift => format!("({lhs}) << ({rhs})"),
+            BinaryOp::RightShift => format!("({lhs}) >> ({rhs})"),
+            BinaryOp::Pop => format!("pop_from_container({lhs}, {rhs})"),
+            BinaryOp::Update => format!("update_container_unique({lhs}, {rhs})"),
+            BinaryOp::Remove => format!("remove_from_list({lhs}, {rhs})"),
         }
     }
 
@@ -378,14 +482,20 @@ impl BinaryOp {
             BinaryOp::Max => lhs.max(&rhs).cloned(),
             BinaryOp::And => lhs.and(&rhs),
             BinaryOp::Or => lhs.or(&rhs),
+            BinaryOp::Xor => lhs.xor(&rhs),
+            BinaryOp::LeftShift => lhs.left_shift(&rhs),
+            BinaryOp::RightShift => lhs.right_shift(&rhs),
+            BinaryOp::Pop => lhs.pop(&rhs).map(|_| lhs),
+            BinaryOp::Update => lhs.update(&rhs).map(|_| lhs),
+            BinaryOp::Remove => lhs.remove(&rhs).map(|_| lhs),
         };
 
         result.map(PythonValue::from).map_err(|err| err.to_string())
     }
 }
 
-fn test_binop(binop: BinaryOp) {
-    let values = list![
+fn values() -> PythonValue {
+    list![
         BigInt::from(i128::MIN) + BigInt::from(i128::MIN),
         i128::MIN,
         i64::MIN,
@@ -419,11 +529,92 @@ fn test_binop(binop: BinaryOp) {
         f64::MAX,
         f64::INFINITY,
         f64::NAN,
-    ];
+        false,
+        true,
+        set![],
+        set!["a", "b"],
+        set!["b", "c"],
+        dict! {},
+        dict! {"a" => 1},
+        dict! {"a" => 2, "b" => 3},
+    ]
+}
+
+/// The values the `remove`, `pop` and `update` operations are applied to
+fn containers() -> PythonValue {
+    list![
+        list![],
+        list![1, 2, 3],
+        list![1, "a", 1],
+        list![list![1]],
+        dict! {},
+        dict! {"a" => 1, "b" => 2},
+        set![],
+        set!["a", "b"],
+        0,
+        "ab",
+        true,
+        PythonValue::None,
+    ]
+}
+
+/// The arguments of the `remove`, `pop` and `update` operations.
+/// Sets have at most one entry, since their iteration order differs from Python.
+fn arguments() -> PythonValue {
+    list![
+        BigInt::from(i128::MIN) + BigInt::from(i128::MIN),
+        -4,
+        -3,
+        -1,
+        0,
+        1,
+        2,
+        3,
+        BigInt::from(u128::MAX) + BigInt::from(u128::MAX),
+        1.0f64,
+        false,
+        true,
+        "a",
+        "b",
+        "ab",
+        PythonValue::None,
+        list![],
+        list![1],
+        list![4],
+        list![1, 4, 4],
+        list!["cd"],
+        list![list![5]],
+        list![list!["c", 3]],
+        dict! {"b" => 3, "c" => 4},
+        set![],
+        set!["b"],
+    ]
+}
+
+fn test_unary_op(unary_op: UnaryOp) {
+    for value in values().as_list() {
+        let code = unary_op.to_python(value);
+        let python_result = eval_python(&code);
+        let rust_result = unary_op.eval_rust(value);
+
+        compare_results(&code, rust_result, python_result);
+    }
+}
+
+fn test_binop(binop: BinaryOp) {
+    let values = values();
     let values = values.as_list();
 
-    for lhs in values {
-        for rhs in values {
+    test_binop_with(binop, values, values);
+}
+
+fn test_container_op(binop: BinaryOp) {
+    test_binop_with(binop, containers().as_list(), arguments().as_list());
+}
+
+fn test_binop_with(binop: BinaryOp, lhs_values: &[PythonValue], rhs_values: &[PythonValue]) {
+    for lhs in lhs_values {
+        for rhs in rhs_values {
             let code = binop.to_python(lhs, rhs);
 
             if !resulting_size_is_ok(&binop, lhs, rhs) {
@@ -431,29 +622,51 @@ fn test_binop(binop: BinaryOp) {
                 continue;
             }
 
+            if is_string_formatting(&binop, lhs, rhs) {
+                continue;
+            }
+
             let python_result = eval_python(&code);
             let rust_result = binop.eval_rust(lhs, rhs);
 
-            match (rust_result, python_result) {
-                (Ok(rust), Ok(python)) => {
-                    if rust != python {
-                        eprintln!("Code: {code}");
-                    }
-                    assert_eq!(rust, python)
-                }
-                (Ok(rust), Err(python)) => {
-                    eprintln!("Code: {code}");
-                    panic!("  Rust: {rust:?}\nPython: {python:?}");
-                }
-                (Err(rust), Ok(python)) => {
-                    eprintln!("Code: {code}");
-                    panic!("  Rust: {rust:?}\nPython: {python:?}");
-                }
-                (Err(_rust), Err(_python)) => {
-                    // TODO: figure out how to verify these are the same
-                    // eprintln!("  Rust: {rust:?}\nPython: {python:?}");
-                }
+            if let Ok(PythonValue::Int(python)) = &python_result
+                && python.bits() > Int::MAX_BITS
+            {
+                assert!(rust_result.is_err(), "{code} exceeds Int::MAX_BITS");
+                continue;
             }
+
+            compare_results(&code, rust_result, python_result);
+        }
+    }
+}
+
+fn compare_results(
+    code: &str,
+    rust_result: Result<PythonValue, String>,
+    python_result: Result<PythonValue, String>,
+) {
+    match (rust_result, python_result) {
+        (Ok(rust), Ok(python)) => {
+            let rust = rust.normalized();
+            let python = python.normalized();
+
+            if rust != python {
+                eprintln!("Code: {code}");
+            }
+            assert_eq!(rust, python)
+        }
+        (Ok(rust), Err(python)) => {
+            eprintln!("Code: {code}");
+            panic!("  Rust: {rust:?}\nPython: {python:?}");
+        }
+        (Err(rust), Ok(python)) => {
+            eprintln!("Code: {code}");
+            panic!("  Rust: {rust:?}\nPython: {python:?}");
+        }
+        (Err(_rust), Err(_python)) => {
+            // TODO: figure out how to verify these are the same
+            // eprintln!("  Rust: {rust:?}\nPython: {python:?}");
         }
     }
 }
@@ -465,7 +678,13 @@ fn resulting_size_is_ok(op: &BinaryOp, lhs: &PythonValue, rhs: &PythonValue) ->
         | BinaryOp::Max
         | BinaryOp::And
         | BinaryOp::Or
-        | BinaryOp::Mod => true,
+        | BinaryOp::Xor
+        | BinaryOp::RightShift
+        | BinaryOp::Mod
+        | BinaryOp::Pop
+        | BinaryOp::Update
+        | BinaryOp::Remove => true,
+        BinaryOp::LeftShift => is_small_int_or_other(rhs),
         BinaryOp::Mul | BinaryOp::Pow => {
             let cond1 = !can_become_big(lhs) || is_small_int_or_other(rhs);
             let cond2 = !can_become_big(rhs) || is_small_int_or_other(lhs);
@@ -475,17 +694,24 @@ fn resulting_size_is_ok(op: &BinaryOp, lhs: &PythonValue, rhs: &PythonValue) ->
     }
 }
 
+/// `str % dict` formats the string with the dict, which is not supported
+fn is_string_formatting(op: &BinaryOp, lhs: &PythonValue, rhs: &PythonValue) -> bool {
+    matches!(
+        (op, lhs, rhs),
+        (BinaryOp::Mod, PythonValue::Str(_), PythonValue::Dict(_))
+    )
+}
+
 fn can_become_big(value: &PythonValue) -> bool {
     match value {
         PythonValue::Int(_)
+        | PythonValue::Bool(_)
         | PythonValue::Str(_)
         | PythonValue::List(_)
         | PythonValue::Tuple(_) => true,
-        PythonValue::Float(_)
-        | PythonValue::Bool(_)
-        | PythonValue::Dict(_)
-        | PythonValue::Set(_)
-        | PythonValue::None => false,
+        PythonValue::Float(_) | PythonValue::Dict(_) | PythonValue::Set(_) | PythonValue::None => {
+            false
+        }
     }
 }
 
@@ -513,6 +739,34 @@ fn test_pow() {
     test_binop(BinaryOp::Pow);
 }
 
+#[test]
+fn test_pow_is_bounded() {
+    let two = Value::int(2);
+
+    assert_eq!(
+        two.pow(&Value::int(Int::MAX_BITS - 1))
+            .unwrap()
+            .as_int()
+            .unwrap()
+            .bits(),
+        Int::MAX_BITS
+    );
+    assert!(two.pow(&Value::int(Int::MAX_BITS)).is_err());
+    assert!(two.pow(&Value::int(u32::MAX)).is_err());
+    assert!(Value::int(-1).pow(&Value::int(u32::MAX)).is_ok());
+}
+
+#[test]
+fn test_mul_is_bounded() {
+    let half = Value::int(1)
+        .left_shift(&Value::int(Int::MAX_BITS / 2))
+        .unwrap();
+
+    assert!(half.mul(&half).is_err());
+    assert!(Value::str("hi").mul(&Value::int(u32::MAX)).is_err());
+    assert!(Value::int(i64::MAX).mul(&Value::str("hi")).is_err());
+}
+
 #[test]
 fn test_mod() {
     test_binop(BinaryOp::Mod);
@@ -538,17 +792,93 @@ fn test_or() {
     test_binop(BinaryOp::Or);
 }
 
+#[test]
+fn test_xor() {
+    test_binop(BinaryOp::Xor);
+}
+
+#[test]
+fn test_left_shift() {
+    test_binop(BinaryOp::LeftShift);
+}
+
+#[test]
+fn test_left_shift_is_bounded() {
+    let one = Value::int(1);
+    let max_count = Value::int(Int::MAX_BITS - 1);
+
+    assert_eq!(
+        one.left_shift(&max_count).unwrap().as_int().unwrap().bits(),
+        Int::MAX_BITS
+    );
+    assert!(one.left_shift(&Value::int(Int::MAX_BITS)).is_err());
+    assert!(one.left_shift(&Value::int(u32::MAX)).is_err());
+    assert!(one.left_shift(&Value::int(u64::MAX)).is_err());
+    assert!(Value::int(0).left_shift(&Value::int(u32::MAX)).is_ok());
+}
+
+#[test]
+fn test_right_shift() {
+    test_binop(BinaryOp::RightShift);
+}
+
 #[test]
 fn test_pop() {
-    todo!()
+    test_container_op(BinaryOp::Pop);
 }
 
 #[test]
 fn test_update() {
-    todo!()
+    test_container_op(BinaryOp::Update);
 }
 
 #[test]
 fn test_remove() {
-    todo!()
+    test_container_op(BinaryOp::Remove);
+}
+
+#[test]
+fn test_deep_copy() {
+    let original = dict! {"a" => list![1, set!["b"]], "c" => dict! {"d" => list![]}};
+    let value = original.to_rust();
+    let copy = value.deep_copy();
+
+    let list = copy
+        .as_dict()
+        .unwrap()
+        .read()
+        .get(&Value::str("a"))
+        .cloned();
+    let list = list.unwrap();
+    list.pop(&Value::int(0)).unwrap();
+    list.as_list()
+        .unwrap()
+        .get(0)
+        .unwrap()
+        .update(&Value::str("e"))
+        .unwrap();
+
+    let dict = copy
+        .as_dict()
+        .unwrap()
+        .read()
+        .get(&Value::str("c"))
+        .cloned();
+    dict.unwrap().update(&dict! {"d" => 2}.to_rust()).unwrap();
+
+    assert_eq!(PythonValue::from(value).normalized(), original.normalized());
+    assert_eq!(
+        PythonValue::from(copy).normalized(),
+        dict! {"a" => list![set!["b", "e"]], "c" => dict! {"d" => 2}}.normalized(),
+    );
+}
+
+#[test]
+fn test_floor() {
+    test_unary_op(UnaryOp::Floor);
+}
+
+#[test]
+fn test_ceil() {
+    test_unary_op(UnaryOp::Ceil);
 }